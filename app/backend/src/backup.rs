@@ -0,0 +1,158 @@
+use std::{fs, path::PathBuf};
+
+use semver::Version;
+use serde::{Deserialize, Deserializer, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tracing::info;
+
+use crate::{
+    config::{parse_config, replace_config, Config},
+    error::{self, Error},
+    extensions::{
+        download_extension, download_extension_release, emit_extensions_update,
+        get_installed_extensions, install_extension, installed_checksum, valid_id,
+    },
+    AppState, PKG_VERSION,
+};
+
+/// A complete pointy setup, including everything needed to reinstall its extensions
+#[derive(Serialize, Deserialize)]
+pub struct SetupExport {
+    pub pointy_version: String,
+    #[serde(deserialize_with = "deserialize_config")]
+    pub config: Config,
+    pub extensions: Vec<ExportedExtension>,
+}
+
+/// An installed extension as part of an export
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportedExtension {
+    pub id: String,
+    pub version: Version,
+    pub latest_url: String,
+    /// Checksum of the installed asset, unknown for extensions installed by older versions
+    pub checksum: Option<String>,
+}
+
+/// Changes an import makes (or would make in a dry run)
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+    /// Extensions which aren't installed yet
+    pub install: Vec<String>,
+    /// Installed extensions which are older than the exported ones
+    pub update: Vec<String>,
+    /// Installed extensions which aren't part of the export, these are kept
+    pub extra: Vec<String>,
+    /// Whether the config differs from the current one
+    pub config_changed: bool,
+}
+
+fn deserialize_config<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Config, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    parse_config(value).map_err(serde::de::Error::custom)
+}
+
+/// Exports the config and the installed extensions to `path`
+#[tauri::command]
+pub fn export_setup(path: PathBuf, app_state: State<'_, AppState>) -> error::Result<()> {
+    let config = app_state.config.read()?.clone();
+
+    let extensions = get_installed_extensions(app_state.clone())?
+        .into_iter()
+        .map(|e| ExportedExtension {
+            checksum: installed_checksum(&app_state.extensions_path, &e.manifest.id),
+            id: e.manifest.id,
+            version: e.manifest.version,
+            latest_url: e.manifest.latest_url,
+        })
+        .collect();
+
+    let export = SetupExport {
+        pointy_version: PKG_VERSION.to_string(),
+        config,
+        extensions,
+    };
+    fs::write(&path, serde_json::to_string_pretty(&export)?)?;
+
+    info!(path = %path.display(), "exported setup");
+    Ok(())
+}
+
+/// Restores a setup exported by [`export_setup`], installing missing extensions
+/// at their exported version. With `dry_run` only reports what would change.
+#[tauri::command]
+pub async fn import_setup(
+    path: PathBuf,
+    dry_run: bool,
+    app: AppHandle,
+) -> error::Result<ImportReport> {
+    let app_state = app.state::<AppState>();
+
+    let data = fs::read_to_string(&path)?;
+    let export: SetupExport = serde_json::from_str(&data)?;
+    for extension in &export.extensions {
+        valid_id(&extension.id)?;
+    }
+
+    let installed = get_installed_extensions(app_state.clone())?;
+    let mut report = ImportReport::default();
+
+    let mut to_install = Vec::new();
+    for extension in &export.extensions {
+        match installed.iter().find(|i| i.manifest.id == extension.id) {
+            None => {
+                report.install.push(extension.id.clone());
+                to_install.push(extension);
+            }
+            Some(i) if i.manifest.version < extension.version => {
+                report.update.push(extension.id.clone());
+                to_install.push(extension);
+            }
+            Some(_) => {}
+        }
+    }
+    report.extra = installed
+        .iter()
+        .map(|i| &i.manifest.id)
+        .filter(|id| !export.extensions.iter().any(|e| &e.id == *id))
+        .cloned()
+        .collect();
+
+    {
+        let current = app_state.config.read()?;
+        report.config_changed = current.autolaunch != export.config.autolaunch
+            || current.activation != export.config.activation
+            || current.active_profile != export.config.active_profile
+            || current.profiles != export.config.profiles;
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    for extension in to_install {
+        let release = download_extension_release(&extension.latest_url, &extension.version).await?;
+        // The release has to contain the exported build, which may be that of another platform
+        if let Some(checksum) = &extension.checksum {
+            if !release.assets.values().any(|a| &a.checksum == checksum) {
+                return Err(Error::Checksum);
+            }
+        }
+        let bytes = download_extension(&release.assets).await?;
+        install_extension(&extension.id, bytes, app_state.clone()).await?;
+        info!(id = %extension.id, version = %release.version, "installed imported extension");
+    }
+
+    if report.config_changed {
+        app_state.config.update(|config| {
+            let mut new_config = export.config.clone();
+            new_config.revision = config.revision;
+            replace_config(&app, config, new_config)
+        })?;
+    }
+
+    emit_extensions_update(&app)?;
+
+    info!(path = %path.display(), "imported setup");
+    Ok(report)
+}
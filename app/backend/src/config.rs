@@ -1,24 +1,232 @@
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{RwLock, RwLockReadGuard},
+};
 
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Emitter, State};
 use tauri_plugin_autostart::ManagerExt;
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+use tracing::warn;
 
-use crate::{error, extensions::emit_extensions_update, AppState};
+use crate::{
+    error::{self, Error},
+    extensions::{emit_extensions_update, installed_extension_ids, EXTENSIONS_URL},
+    shortcuts, AppState,
+};
+
+pub const DEFAULT_PROFILE: &str = "default";
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
+    /// Increased on every change, used to reject writes based on an outdated config
+    #[serde(default)]
+    pub revision: u64,
     pub autolaunch: bool,
+    /// How the wheel shortcuts open and select
+    #[serde(default)]
+    pub activation: Activation,
+    /// Seconds after which a run of an extension is asked to stop
+    #[serde(default = "default_run_timeout")]
+    pub run_timeout: u64,
+    /// MiB of memory a run of an extension may use, only enforced on Linux
+    #[serde(default = "default_memory_limit")]
+    pub memory_limit: u64,
+    /// Limits of the runs of extensions by id, overriding the defaults above
+    #[serde(default)]
+    pub limits: BTreeMap<String, RunLimits>,
+    /// Extensions disabled after failing repeatedly by id, with the reason
+    #[serde(default)]
+    pub quarantined: BTreeMap<String, String>,
+    /// Url of the extension registry, links can only install extensions listed in it
+    #[serde(default = "default_registry")]
+    pub registry: String,
+    /// Records the runs of extensions with their output, shown in the settings
+    #[serde(default)]
+    pub developer_console: bool,
+    /// Name of the profile currently in use
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// The profile currently in use
+    pub fn profile(&self) -> &Profile {
+        &self.profiles[&self.active_profile]
+    }
+
+    /// The profile currently in use
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        self.profiles
+            .get_mut(&self.active_profile)
+            .expect("active profile exists")
+    }
+
+    /// Makes sure there is at least one profile and that the active one exists
+    pub fn validate(&mut self) {
+        if self.profiles.is_empty() {
+            self.profiles
+                .insert(DEFAULT_PROFILE.to_string(), Profile::default());
+        }
+        if !self.profiles.contains_key(&self.active_profile) {
+            self.active_profile = self.profiles.keys().next().cloned().unwrap_or_default();
+        }
+        self.run_timeout = self.run_timeout.max(1);
+        self.memory_limit = self.memory_limit.max(1);
+
+        // Enabling an extension again lifts its quarantine
+        let profiles = &self.profiles;
+        self.quarantined
+            .retain(|id, _| !profiles.values().any(|p| p.enabled.contains(id)));
+    }
+
+    /// Disables extension `id` in all profiles until it is enabled again
+    pub fn quarantine(&mut self, id: &str, reason: String) {
+        for profile in self.profiles.values_mut() {
+            profile.enabled.retain(|e| e != id);
+        }
+        self.quarantined.insert(id.to_string(), reason);
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            revision: 0,
+            autolaunch: false,
+            activation: Activation::default(),
+            run_timeout: default_run_timeout(),
+            memory_limit: default_memory_limit(),
+            limits: BTreeMap::new(),
+            quarantined: BTreeMap::new(),
+            registry: default_registry(),
+            developer_console: false,
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), Profile::default())]),
+        }
+    }
+}
+
+fn default_run_timeout() -> u64 {
+    30
+}
+
+fn default_memory_limit() -> u64 {
+    1024
+}
+
+fn default_registry() -> String {
+    EXTENSIONS_URL.to_string()
+}
+
+/// Limits of the runs of an extension, unset ones use the defaults of the config
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RunLimits {
+    /// Seconds after which a run is asked to stop
+    #[serde(default)]
+    pub wall_time: Option<u64>,
+    /// Seconds of CPU time, unlimited by default
+    #[serde(default)]
+    pub cpu_time: Option<u64>,
+    /// MiB of memory
+    #[serde(default)]
+    pub memory: Option<u64>,
+}
+
+/// How the wheel shortcuts open and select
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Activation {
+    pub mode: ActivationMode,
+    /// Radius around the wheel's centre in logical pixels in which selecting cancels instead
+    pub dead_zone: f64,
+}
+
+impl Default for Activation {
+    fn default() -> Self {
+        Self {
+            mode: ActivationMode::default(),
+            dead_zone: 16.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivationMode {
+    /// Open on press, select on release
+    #[default]
+    HoldRelease,
+    /// Open on press, select on click or the next press
+    Toggle,
+    /// Open on pressing twice quickly, select on click or the next press
+    DoubleTap,
+}
+
+/// A named set of extensions with its own shortcut
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Profile {
     pub shortcut: String,
     pub enabled: Vec<String>,
     pub ordered: Vec<String>,
+    /// Shortcut opening the palette, disabled if `None`
+    #[serde(default)]
+    pub palette_shortcut: Option<String>,
+    /// Shortcuts running an extension directly, by extension id
+    #[serde(default)]
+    pub hotkeys: BTreeMap<String, String>,
+    /// Additional wheels by name
+    #[serde(default)]
+    pub wheels: BTreeMap<String, Wheel>,
+    /// Groups opening a sub-wheel by id, referenced like extensions in the wheels
+    #[serde(default)]
+    pub groups: BTreeMap<String, Group>,
 }
 
-impl Default for Config {
+impl Profile {
+    /// Returns a group which (indirectly) contains itself
+    pub fn group_cycle(&self) -> Option<&String> {
+        fn visit<'a>(
+            groups: &'a BTreeMap<String, Group>,
+            id: &'a String,
+            path: &mut Vec<&'a String>,
+        ) -> Option<&'a String> {
+            if path.contains(&id) {
+                return Some(id);
+            }
+            let group = groups.get(id)?;
+            path.push(id);
+            let cycle = group.entries.iter().find_map(|e| visit(groups, e, path));
+            path.pop();
+            cycle
+        }
+
+        self.groups
+            .keys()
+            .find_map(|id| visit(&self.groups, id, &mut Vec::new()))
+    }
+}
+
+/// A slot opening a sub-wheel
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Group {
+    pub name: String,
+    /// Extensions and groups shown in the sub-wheel, in order
+    pub entries: Vec<String>,
+}
+
+/// An additional wheel with its own shortcut
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Wheel {
+    pub shortcut: String,
+    /// Extensions shown in the wheel, in order
+    pub extensions: Vec<String>,
+}
+
+impl Default for Profile {
     fn default() -> Self {
         Self {
-            autolaunch: false,
             shortcut: {
                 #[cfg(target_os = "macos")]
                 {
@@ -31,79 +239,640 @@ impl Default for Config {
             },
             enabled: vec![],
             ordered: vec![],
+            palette_shortcut: None,
+            hotkeys: BTreeMap::new(),
+            wheels: BTreeMap::new(),
+            groups: BTreeMap::new(),
         }
     }
 }
 
+/// Config layout before profiles were introduced
+#[derive(Deserialize)]
+struct LegacyConfig {
+    #[serde(default)]
+    revision: u64,
+    autolaunch: bool,
+    shortcut: String,
+    enabled: Vec<String>,
+    ordered: Vec<String>,
+}
+
+impl From<LegacyConfig> for Config {
+    fn from(legacy: LegacyConfig) -> Self {
+        let profile = Profile {
+            shortcut: legacy.shortcut,
+            enabled: legacy.enabled,
+            ordered: legacy.ordered,
+            palette_shortcut: None,
+            hotkeys: BTreeMap::new(),
+            wheels: BTreeMap::new(),
+            groups: BTreeMap::new(),
+        };
+        Self {
+            revision: legacy.revision,
+            autolaunch: legacy.autolaunch,
+            activation: Activation::default(),
+            run_timeout: default_run_timeout(),
+            memory_limit: default_memory_limit(),
+            limits: BTreeMap::new(),
+            quarantined: BTreeMap::new(),
+            registry: default_registry(),
+            developer_console: false,
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), profile)]),
+        }
+    }
+}
+
+/// Owns the app config and is the only place persisting it to disk.
+///
+/// All writers go through [`ConfigStore::update`], which changes a copy and only commits it
+/// if the config didn't change in the meantime. The lock is only held while committing,
+/// never while shortcuts are registered, as that waits for the main thread reading the config.
+/// Every change is broadcast to all windows as a `config-changed` event
+/// and the registered global shortcuts are kept in sync with it.
+pub struct ConfigStore {
+    file: ConfigFile,
+    config: RwLock<Config>,
+    app: AppHandle,
+}
+
+impl ConfigStore {
+    /// Opens the config at `path`, see [`ConfigFile::load`]
+    pub fn open(path: PathBuf, app: AppHandle) -> error::Result<Self> {
+        let file = ConfigFile { path };
+        let config = file.load()?;
+
+        Ok(Self {
+            file,
+            config: RwLock::new(config),
+            app,
+        })
+    }
+
+    /// Locks the current config for reading
+    pub fn read(&self) -> error::Result<RwLockReadGuard<'_, Config>> {
+        Ok(self.config.read()?)
+    }
+
+    /// Applies `f` to a copy of the current config and persists the result.
+    /// The in-memory config is only replaced if `f` and the write succeeded,
+    /// `f` is applied again if another change was committed first.
+    pub fn update(
+        &self,
+        mut f: impl FnMut(&mut Config) -> error::Result<()>,
+    ) -> error::Result<Config> {
+        loop {
+            match self.update_inner(None, &mut f) {
+                Err(Error::StaleRevision(_)) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Like [`ConfigStore::update`], but fails with [`Error::StaleRevision`]
+    /// if the config changed since `revision` was read.
+    pub fn update_at(
+        &self,
+        revision: u64,
+        f: impl FnOnce(&mut Config) -> error::Result<()>,
+    ) -> error::Result<Config> {
+        self.update_inner(Some(revision), f)
+    }
+
+    fn update_inner(
+        &self,
+        revision: Option<u64>,
+        f: impl FnOnce(&mut Config) -> error::Result<()>,
+    ) -> error::Result<Config> {
+        let config = self.config.read()?.clone();
+        let new_config = apply(&config, revision, f)?;
+
+        shortcuts::sync(&self.app, &config, &new_config)?;
+        if let Err(e) = self.commit(&config, &new_config) {
+            let _ = shortcuts::sync(&self.app, &new_config, &config);
+            return Err(e);
+        }
+
+        self.notify(&config, &new_config);
+
+        Ok(new_config)
+    }
+
+    /// Persists `new_config` and makes it the current one,
+    /// unless the config changed since `config` was read
+    fn commit(&self, config: &Config, new_config: &Config) -> error::Result<()> {
+        let mut current = self.config.write()?;
+        if current.revision != config.revision {
+            return Err(Error::StaleRevision(current.revision));
+        }
+        self.file.save(new_config)?;
+        *current = new_config.clone();
+        Ok(())
+    }
+
+    /// Informs the windows about a config change
+    fn notify(&self, old_config: &Config, new_config: &Config) {
+        if let Err(e) = self.app.emit("config-changed", new_config) {
+            warn!(error = %e, "failed to emit config change");
+        }
+
+        // Emit an extension update if the wheels of the active profile changed
+        let (old_profile, new_profile) = (old_config.profile(), new_config.profile());
+        if new_profile.enabled != old_profile.enabled
+            || new_profile.ordered != old_profile.ordered
+            || new_profile.wheels != old_profile.wheels
+            || new_profile.groups != old_profile.groups
+        {
+            if let Err(e) = emit_extensions_update(&self.app) {
+                warn!(error = %e, "failed to emit extensions update");
+            }
+        }
+    }
+}
+
+/// Applies `f` to a copy of `config`, which becomes the next revision.
+/// Fails with [`Error::StaleRevision`] if `revision` is given and not the current one.
+fn apply(
+    config: &Config,
+    revision: Option<u64>,
+    f: impl FnOnce(&mut Config) -> error::Result<()>,
+) -> error::Result<Config> {
+    if revision.is_some_and(|r| r != config.revision) {
+        return Err(Error::StaleRevision(config.revision));
+    }
+
+    let mut new_config = config.clone();
+    f(&mut new_config)?;
+    new_config.revision = config.revision + 1;
+    Ok(new_config)
+}
+
+/// The config file on disk along with the backup of its previous version
+struct ConfigFile {
+    path: PathBuf,
+}
+
+impl ConfigFile {
+    /// Loads the config, creating a default one if it doesn't exist.
+    /// A corrupted config is recovered from its backup if possible.
+    fn load(&self) -> error::Result<Config> {
+        if !self.path.exists() {
+            let config = Config::default();
+            write_atomic(&self.path, serde_json::to_string(&config)?.as_bytes())?;
+            return Ok(config);
+        }
+
+        match load_config(&self.path) {
+            Ok(config) => Ok(config),
+            Err(e) => {
+                warn!(error = %e, path = %self.path.display(), "config is corrupted, restoring backup");
+                let config = load_config(&backup_path(&self.path))?;
+                write_atomic(&self.path, serde_json::to_string(&config)?.as_bytes())?;
+                Ok(config)
+            }
+        }
+    }
+
+    /// Writes `config` atomically, keeping the previous file as backup
+    fn save(&self, config: &Config) -> error::Result<()> {
+        let data = serde_json::to_string(config)?;
+
+        // Written like the config itself, so a crash never leaves a partial backup behind
+        if self.path.exists() {
+            write_atomic(&backup_path(&self.path), &fs::read(&self.path)?)?;
+        }
+        write_atomic(&self.path, data.as_bytes())
+    }
+}
+
 /// Loads the app config from a path
-pub fn load_config(path: &PathBuf) -> tauri::Result<Config> {
+fn load_config(path: &Path) -> error::Result<Config> {
     let data = fs::read_to_string(path)?;
-    Ok(serde_json::from_str(&data)?)
+    parse_config(serde_json::from_str(&data)?)
+}
+
+/// Parses a stored config, migrating older layouts
+pub fn parse_config(value: serde_json::Value) -> error::Result<Config> {
+    let mut config: Config = if value.get("profiles").is_some() {
+        serde_json::from_value(value)?
+    } else {
+        serde_json::from_value::<LegacyConfig>(value)?.into()
+    };
+    config.validate();
+
+    Ok(config)
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+/// Writes `data` to a temporary file next to `path`, syncs it and renames it over `path`.
+/// A crash at any point leaves either the old or the new file behind, never a partial one.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> error::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
 }
 
 /// Fetches the current config from the state
 #[tauri::command]
 pub fn get_config(app_state: State<'_, AppState>) -> error::Result<Config> {
-    let config = app_state.config.read()?;
-    Ok(config.clone())
+    Ok(app_state.config.read()?.clone())
 }
 
 /// Changes stored config and saves it to disk. Also applies changes of the config to the app
 #[tauri::command]
 pub fn change_config(
-    new_config: Config,
+    mut new_config: Config,
     app: AppHandle,
     app_state: State<'_, AppState>,
 ) -> error::Result<Config> {
-    let mut config = app_state.config.write()?;
-    let old_config = config.clone();
+    new_config.validate();
 
-    // Only update when the shortcut string actually changed
-    if old_config.shortcut != new_config.shortcut {
-        // First, try registering the new shortcut.
-        // We register before unregistering the old one so that
-        // if the new shortcut is invalid or conflicts, the old shortcut
-        // remains active and the app stays functional.
-        let new_shortcut = Shortcut::from_str(&new_config.shortcut)?;
-        app.global_shortcut().register(new_shortcut)?;
-
-        // Now that the new shortcut is live, remove the old registration
-        let old_shortcut = Shortcut::from_str(&old_config.shortcut)?;
-        app.global_shortcut().unregister(old_shortcut)?;
-    }
+    app_state.config.update_at(new_config.revision, |config| {
+        replace_config(&app, config, new_config)
+    })
+}
 
+/// Replaces `config` by `new_config`, applying the changed autolaunch setting
+pub fn replace_config(
+    app: &AppHandle,
+    config: &mut Config,
+    new_config: Config,
+) -> error::Result<()> {
     // Only update when autolaunch actually changed
-    if new_config.autolaunch != old_config.autolaunch {
-        set_autolaunch(&new_config, &app)?;
+    if new_config.autolaunch != config.autolaunch {
+        apply_autolaunch(&new_config, app)?;
     }
 
-    // Save to app state
-    *config = new_config.clone();
-    drop(config);
+    *config = new_config;
+    Ok(())
+}
+
+/// Enables or disables a single extension
+#[tauri::command]
+pub fn set_extension_enabled(
+    id: String,
+    enabled: bool,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        let profile = config.profile_mut();
+        profile.enabled.retain(|e| e != &id);
+        if enabled {
+            profile.enabled.push(id.clone());
+            config.quarantined.remove(&id);
+        }
+        Ok(())
+    })
+}
+
+/// Moves an extension to `index` in the wheel order
+#[tauri::command]
+pub fn move_extension(
+    id: String,
+    index: usize,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    let installed = installed_extension_ids(&app_state.extensions_path)?;
+
+    app_state.config.update_at(revision, |config| {
+        // Extensions without a position are ranked after the ordered ones
+        let profile = config.profile_mut();
+        let mut ordered = profile.ordered.clone();
+        for installed_id in installed {
+            if !ordered.contains(&installed_id) {
+                ordered.push(installed_id);
+            }
+        }
+
+        ordered.retain(|e| e != &id);
+        ordered.insert(index.min(ordered.len()), id);
+
+        profile.ordered = ordered;
+        Ok(())
+    })
+}
+
+/// Changes the shortcut opening the wheel
+#[tauri::command]
+pub fn set_shortcut(
+    shortcut: String,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        config.profile_mut().shortcut = shortcut;
+        Ok(())
+    })
+}
+
+/// Changes the shortcut opening the palette, or disables it if `shortcut` is `None`
+#[tauri::command]
+pub fn set_palette_shortcut(
+    shortcut: Option<String>,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        config.profile_mut().palette_shortcut = shortcut;
+        Ok(())
+    })
+}
 
-    // Persist config
-    fs::write(&app_state.config_path, serde_json::to_string(&new_config)?)?;
+/// Binds a shortcut running extension `id` directly, or removes it if `shortcut` is `None`
+#[tauri::command]
+pub fn set_extension_shortcut(
+    id: String,
+    shortcut: Option<String>,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        let hotkeys = &mut config.profile_mut().hotkeys;
+        match shortcut {
+            Some(shortcut) => hotkeys.insert(id, shortcut),
+            None => hotkeys.remove(&id),
+        };
+        Ok(())
+    })
+}
 
-    // Emit an extension update if enabled or ordered changed
-    if new_config.enabled != old_config.enabled || new_config.ordered != old_config.ordered {
-        emit_extensions_update(&app)?;
+/// Creates or replaces the wheel `name`, or removes it if `wheel` is `None`
+#[tauri::command]
+pub fn set_wheel(
+    name: String,
+    wheel: Option<Wheel>,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(Error::Wheel("wheel name must not be empty".into()));
     }
 
-    Ok(new_config)
+    app_state.config.update_at(revision, |config| {
+        let wheels = &mut config.profile_mut().wheels;
+        match wheel {
+            Some(wheel) => {
+                wheels.insert(name, wheel);
+            }
+            None => {
+                wheels
+                    .remove(&name)
+                    .ok_or_else(|| Error::Wheel(format!("wheel '{name}' does not exist")))?;
+            }
+        }
+        Ok(())
+    })
 }
 
+/// Creates or replaces the group `id`, or removes it and all references to it if `group` is `None`
+#[tauri::command]
+pub fn set_group(
+    id: String,
+    group: Option<Group>,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    let id = id.trim().to_string();
+    if id.is_empty() {
+        return Err(Error::Wheel("group id must not be empty".into()));
+    }
+    // Groups are referenced like extensions, so their ids must not overlap
+    if installed_extension_ids(&app_state.extensions_path)?.contains(&id) {
+        return Err(Error::Wheel(format!("'{id}' is already an extension")));
+    }
+
+    app_state.config.update_at(revision, |config| {
+        let profile = config.profile_mut();
+        match group {
+            Some(group) => {
+                profile.groups.insert(id, group);
+                if let Some(cycle) = profile.group_cycle() {
+                    return Err(Error::Wheel(format!("group '{cycle}' contains itself")));
+                }
+            }
+            None => {
+                profile
+                    .groups
+                    .remove(&id)
+                    .ok_or_else(|| Error::Wheel(format!("group '{id}' does not exist")))?;
+
+                profile.enabled.retain(|e| e != &id);
+                profile.ordered.retain(|e| e != &id);
+                for wheel in profile.wheels.values_mut() {
+                    wheel.extensions.retain(|e| e != &id);
+                }
+                for group in profile.groups.values_mut() {
+                    group.entries.retain(|e| e != &id);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Changes how the wheel shortcuts open and select
+#[tauri::command]
+pub fn set_activation(
+    activation: Activation,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        config.activation = activation;
+        Ok(())
+    })
+}
+
+/// Enables or disables launching on system startup
+#[tauri::command]
 pub fn set_autolaunch(
+    autolaunch: bool,
+    revision: u64,
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        if config.autolaunch != autolaunch {
+            config.autolaunch = autolaunch;
+            apply_autolaunch(config, &app)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn apply_autolaunch(
     config: &Config,
     app: &AppHandle,
 ) -> Result<(), tauri_plugin_autostart::Error> {
     let autostart_manager = app.autolaunch();
     if config.autolaunch {
-        if !autostart_manager.is_enabled()? {
-            autostart_manager.enable()?;
-        }
+        // Always rewritten, so entries of older versions get the current arguments
+        autostart_manager.enable()?;
     } else if autostart_manager.is_enabled()? {
         autostart_manager.disable()?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> ConfigFile {
+        let dir = std::env::temp_dir().join(format!("pointy-config-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ConfigFile {
+            path: dir.join("config.json"),
+        }
+    }
+
+    fn with_revision(revision: u64) -> Config {
+        Config {
+            revision,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn saving_keeps_the_previous_config_as_backup() {
+        let file = temp_file("save");
+        assert_eq!(file.load().unwrap().revision, 0);
+
+        file.save(&with_revision(1)).unwrap();
+        file.save(&with_revision(2)).unwrap();
+        assert_eq!(load_config(&file.path).unwrap().revision, 2);
+        assert_eq!(load_config(&backup_path(&file.path)).unwrap().revision, 1);
+
+        // Nothing is left of the temporary files
+        let dir = file.path.parent().unwrap();
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["config.json", "config.json.bak"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupted_configs_are_restored_from_the_backup() {
+        let file = temp_file("restore");
+        file.save(&with_revision(1)).unwrap();
+        file.save(&with_revision(2)).unwrap();
+
+        // As left behind by a write which isn't atomic
+        fs::write(&file.path, r#"{"revision": 3, "autol"#).unwrap();
+        assert_eq!(file.load().unwrap().revision, 1);
+        assert_eq!(load_config(&file.path).unwrap().revision, 1);
+
+        fs::remove_dir_all(file.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn stale_revisions_are_rejected() {
+        let config = with_revision(3);
+
+        let result = apply(&config, Some(2), |_| Ok(()));
+        assert!(matches!(result, Err(Error::StaleRevision(3))));
+
+        let result = apply(&config, Some(3), |c| {
+            c.autolaunch = true;
+            Ok(())
+        });
+        let new_config = result.unwrap();
+        assert_eq!(new_config.revision, 4);
+        assert!(new_config.autolaunch);
+
+        // Without a revision any is fine
+        assert_eq!(apply(&config, None, |_| Ok(())).unwrap().revision, 4);
+        let result = apply(&config, None, |_| Err(Error::Profile("nope".into())));
+        assert!(matches!(result, Err(Error::Profile(_))));
+    }
+
+    #[test]
+    fn legacy_configs_become_the_default_profile() {
+        let legacy = serde_json::json!({
+            "revision": 7,
+            "autolaunch": true,
+            "shortcut": "Alt+Space",
+            "enabled": ["math", "qrcode"],
+            "ordered": ["qrcode", "math"]
+        });
+        let config = parse_config(legacy).unwrap();
+
+        assert_eq!(config.revision, 7);
+        assert!(config.autolaunch);
+        assert_eq!(config.active_profile, DEFAULT_PROFILE);
+        assert_eq!(config.profiles.len(), 1);
+        let profile = config.profile();
+        assert_eq!(profile.shortcut, "Alt+Space");
+        assert_eq!(profile.enabled, ["math", "qrcode"]);
+        assert_eq!(profile.ordered, ["qrcode", "math"]);
+        assert!(profile.hotkeys.is_empty() && profile.wheels.is_empty());
+        assert_eq!(config.run_timeout, default_run_timeout());
+        assert_eq!(config.registry, default_registry());
+
+        // Stored again it is read in the new layout
+        let stored = serde_json::to_value(&config).unwrap();
+        assert_eq!(parse_config(stored).unwrap().profiles, config.profiles);
+    }
+
+    #[test]
+    fn parsed_configs_have_an_active_profile() {
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        value["active_profile"] = "gone".into();
+        value["run_timeout"] = 0.into();
+        let config = parse_config(value).unwrap();
+        assert_eq!(config.active_profile, DEFAULT_PROFILE);
+        assert_eq!(config.run_timeout, 1);
+
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        value["profiles"] = serde_json::json!({});
+        let config = parse_config(value).unwrap();
+        assert_eq!(config.profile(), &Profile::default());
+    }
+
+    #[test]
+    fn enabling_lifts_the_quarantine() {
+        let mut config = Config::default();
+        config.profile_mut().enabled.push("a".into());
+        config.quarantine("a", "it crashed".into());
+
+        assert!(config.profile().enabled.is_empty());
+        config.validate();
+        assert_eq!(
+            config.quarantined.get("a").map(String::as_str),
+            Some("it crashed")
+        );
+
+        config.profile_mut().enabled.push("a".into());
+        config.validate();
+        assert!(config.quarantined.is_empty());
+    }
+}
@@ -1,10 +1,10 @@
 use std::{fmt, sync::PoisonError};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind", content = "value")]
 pub enum Error {
     /// Lock is poisoned
@@ -13,6 +13,10 @@ pub enum Error {
     Checksum,
     /// No assets found for this platform
     NoAssets,
+    /// The extension id isn't a plain one, it could name a path outside its directory
+    InvalidId(String),
+    /// The requested release of an extension doesn't exist
+    UnknownRelease(String),
     /// File System Error
     FileSystem(String),
     /// Library Loading Error
@@ -29,6 +33,28 @@ pub enum Error {
     Shortcut(String),
     /// Autostart Error
     Autostart(String),
+    /// The lockfile is invalid or unsupported
+    Lockfile(String),
+    /// The extension declares other permissions than the reviewed ones, contains its id
+    PermissionsChanged(String),
+    /// The config was changed in the meantime, contains the current revision
+    StaleRevision(u64),
+    /// Invalid profile operation
+    Profile(String),
+    /// Invalid wheel operation
+    Wheel(String),
+    /// Invalid launch arguments or forwarding them failed
+    Launch(String),
+    /// Invalid extension run operation
+    Run(String),
+    /// The extension run was cancelled
+    Cancelled,
+    /// The extension run didn't finish within the timeout, contains the seconds
+    TimedOut(u64),
+    /// The runner exited without the extension returning, contains its exit status
+    Crashed(String),
+    /// The extension run exceeded a resource limit, contains which
+    LimitExceeded(String),
 }
 
 impl fmt::Display for Error {
@@ -37,6 +63,8 @@ impl fmt::Display for Error {
             Error::PoisonedLock => write!(f, "internal lock was poisoned"),
             Error::Checksum => write!(f, "checksum verification failed"),
             Error::NoAssets => write!(f, "no assets found for this platform"),
+            Error::InvalidId(id) => write!(f, "invalid extension id '{}'", id),
+            Error::UnknownRelease(v) => write!(f, "release {} not found", v),
             Error::FileSystem(e) => write!(f, "file system error: {}", e),
             Error::LibLoading(e) => write!(f, "library loading error: {}", e),
             Error::Conversion(e) => write!(f, "conversion error: {}", e),
@@ -45,10 +73,35 @@ impl fmt::Display for Error {
             Error::Tauri(e) => write!(f, "Tauri runtime error: {}", e),
             Error::Shortcut(e) => write!(f, "global shortcut error: {}", e),
             Error::Autostart(e) => write!(f, "autostart configuration error: {}", e),
+            Error::Lockfile(e) => write!(f, "lockfile error: {}", e),
+            Error::PermissionsChanged(id) => {
+                write!(
+                    f,
+                    "permissions of extension '{}' changed since reviewed",
+                    id
+                )
+            }
+            Error::StaleRevision(r) => {
+                write!(
+                    f,
+                    "config was changed in the meantime (now at revision {})",
+                    r
+                )
+            }
+            Error::Profile(e) => write!(f, "profile error: {}", e),
+            Error::Wheel(e) => write!(f, "wheel error: {}", e),
+            Error::Launch(e) => write!(f, "launch error: {}", e),
+            Error::Run(e) => write!(f, "extension run error: {}", e),
+            Error::Cancelled => write!(f, "extension run was cancelled"),
+            Error::TimedOut(s) => write!(f, "extension run timed out after {} seconds", s),
+            Error::Crashed(s) => write!(f, "extension crashed: {}", s),
+            Error::LimitExceeded(l) => write!(f, "extension run exceeded its limit of {}", l),
         }
     }
 }
 
+impl std::error::Error for Error {}
+
 impl<T> From<PoisonError<T>> for Error {
     fn from(_: PoisonError<T>) -> Self {
         Error::PoisonedLock
@@ -61,12 +114,6 @@ impl From<std::io::Error> for Error {
     }
 }
 
-impl From<libloading::Error> for Error {
-    fn from(err: libloading::Error) -> Self {
-        Error::LibLoading(err.to_string())
-    }
-}
-
 impl From<std::ffi::IntoStringError> for Error {
     fn from(err: std::ffi::IntoStringError) -> Self {
         Error::Conversion(err.to_string())
@@ -1,24 +1,36 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::{self, File},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use flate2::read::GzDecoder;
+use pointy_api::Permission;
+use pointy_runner::ExtensionKind;
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tar::Archive;
 use tauri::{AppHandle, Emitter, Manager, State};
 
+use tracing::warn;
+
 use crate::{
+    config::Group,
     error::{self, Error},
+    logs,
+    permissions::pending,
     AppState,
 };
 
 pub const EXTENSIONS_URL: &str =
     "https://raw.githubusercontent.com/nwrenger/pointy-extensions/refs/heads/main/extensions.json";
 
+/// File inside an extension directory holding the checksum of the installed asset
+const CHECKSUM_FILE: &str = "checksum";
+/// Key of the asset running on every platform, that of WebAssembly extensions
+pub const WASM_ASSET_KEY: &str = "wasm";
+
 /// Extension metadata
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ExtensionManifest {
@@ -28,10 +40,19 @@ pub struct ExtensionManifest {
     pub version: Version,
     pub description: String,
     pub latest_url: String,
+    /// Additional search terms for the palette
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Capabilities the extension needs, approved by the user on install
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    /// Whether it is a native library or a WebAssembly component
+    #[serde(default)]
+    pub kind: ExtensionKind,
 }
 
 /// Extension download information
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AvailableExtension {
     pub id: String,
     pub name: String,
@@ -45,6 +66,16 @@ pub struct AvailableExtension {
 pub struct Latest {
     pub version: Version,
     pub assets: HashMap<String, Asset>,
+    /// Index of all releases, needed for installing a specific version
+    #[serde(default)]
+    pub releases_url: Option<String>,
+}
+
+/// A specific release of an extension
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Release {
+    pub version: Version,
+    pub assets: HashMap<String, Asset>,
 }
 
 /// A downloadable asset with checksum
@@ -60,6 +91,24 @@ pub struct InstalledExtensionInfo {
     pub manifest: ExtensionManifest,
     pub icon_path: PathBuf,
     pub enabled: bool,
+    /// Declared permissions which weren't approved yet, e.g. after an update asking for more
+    pub pending_permissions: Vec<Permission>,
+}
+
+/// An entry of a wheel, either an extension or a group opening a sub-wheel
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", content = "value")]
+pub enum WheelEntry {
+    Extension(InstalledExtensionInfo),
+    Group(GroupEntry),
+}
+
+/// A group resolved into its entries
+#[derive(Serialize, Clone, Debug)]
+pub struct GroupEntry {
+    pub id: String,
+    pub name: String,
+    pub entries: Vec<WheelEntry>,
 }
 
 /// Returns the extension info of all extensions
@@ -68,9 +117,9 @@ pub fn get_installed_extensions(
     app_state: State<'_, AppState>,
 ) -> error::Result<Vec<InstalledExtensionInfo>> {
     let extensions_path = app_state.extensions_path.clone();
-    let config = app_state.config.read()?.clone();
-    let enabled = config.enabled;
-    let ordered = config.ordered;
+    let profile = app_state.config.read()?.profile().clone();
+    let enabled = profile.enabled;
+    let ordered = profile.ordered;
 
     let dirs = extensions_path.read_dir()?;
 
@@ -86,8 +135,10 @@ pub fn get_installed_extensions(
                 let manifest: ExtensionManifest = serde_json::from_str(&manifest_data)?;
 
                 let icon_path = extensions_path.join(&path).join("icon.svg");
+                let granted = app_state.permissions.granted(&manifest.id)?;
 
                 extensions.push(InstalledExtensionInfo {
+                    pending_permissions: pending(&manifest.permissions, &granted),
                     manifest,
                     icon_path,
                     enabled: enabled.contains(
@@ -107,6 +158,89 @@ pub fn get_installed_extensions(
     Ok(extensions)
 }
 
+/// Returns the entries of the wheel currently shown, with groups resolved into a tree
+#[tauri::command]
+pub fn get_wheel_entries(app_state: State<'_, AppState>) -> error::Result<Vec<WheelEntry>> {
+    let installed = get_installed_extensions(app_state.clone())?;
+    let active_wheel = app_state.wheel.lock()?.active.clone();
+
+    let config = app_state.config.read()?;
+    let profile = config.profile();
+    let ids = match active_wheel.and_then(|name| profile.wheels.get(&name)) {
+        Some(wheel) => wheel.extensions.clone(),
+        // The main wheel shows the enabled extensions and groups of the profile
+        None => {
+            let mut ids = profile.enabled.clone();
+            sort_ids_by_order(&mut ids, &profile.ordered);
+            ids
+        }
+    };
+
+    Ok(resolve_entries(
+        &ids,
+        &profile.groups,
+        &installed,
+        &mut Vec::new(),
+    ))
+}
+
+/// Resolves `ids` into wheel entries, skipping unknown extensions and group cycles
+fn resolve_entries(
+    ids: &[String],
+    groups: &BTreeMap<String, Group>,
+    installed: &[InstalledExtensionInfo],
+    path: &mut Vec<String>,
+) -> Vec<WheelEntry> {
+    ids.iter()
+        .filter_map(|id| {
+            let Some(group) = groups.get(id) else {
+                let extension = installed.iter().find(|e| &e.manifest.id == id)?;
+                return Some(WheelEntry::Extension(InstalledExtensionInfo {
+                    enabled: true,
+                    ..extension.clone()
+                }));
+            };
+
+            if path.contains(id) {
+                warn!(group = %id, "skipping group containing itself");
+                return None;
+            }
+            path.push(id.clone());
+            let entries = resolve_entries(&group.entries, groups, installed, path);
+            path.pop();
+
+            Some(WheelEntry::Group(GroupEntry {
+                id: id.clone(),
+                name: group.name.clone(),
+                entries,
+            }))
+        })
+        .collect()
+}
+
+/// Returns the ids of all installed extensions, sorted alphabetically
+pub fn installed_extension_ids(extensions_path: &Path) -> error::Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for entry in extensions_path.read_dir()? {
+        let path = entry?.path();
+        if path.join("manifest.json").exists() {
+            if let Some(name) = path.file_name() {
+                ids.push(name.to_string_lossy().to_string());
+            }
+        }
+    }
+    ids.sort();
+
+    Ok(ids)
+}
+
+fn sort_ids_by_order(ids: &mut [String], ordered: &[String]) {
+    ids.sort_by_key(|id| {
+        let r = ordered.iter().position(|o| o == id).unwrap_or(usize::MAX);
+        (r, id.clone())
+    });
+}
+
 fn sort_by_order(v: &mut [InstalledExtensionInfo], ordered: &[String]) {
     let rank: HashMap<&str, usize> = ordered
         .iter()
@@ -123,21 +257,35 @@ fn sort_by_order(v: &mut [InstalledExtensionInfo], ordered: &[String]) {
     });
 }
 
-/// Fetches the online extension manifests.
+/// Fetches the online extension manifests of the configured registry.
 #[tauri::command]
-pub async fn fetch_online_extensions() -> error::Result<Vec<AvailableExtension>> {
-    let res = reqwest::get(EXTENSIONS_URL).await?;
+pub async fn fetch_online_extensions(
+    app_state: State<'_, AppState>,
+) -> error::Result<Vec<AvailableExtension>> {
+    let registry = app_state.config.read()?.registry.clone();
+    fetch_registry(&registry).await
+}
+
+/// Fetches the extension manifests listed by the registry at `url`.
+pub async fn fetch_registry(url: &str) -> error::Result<Vec<AvailableExtension>> {
+    let res = reqwest::get(url).await?;
     let extensions: Vec<AvailableExtension> = res.json().await?;
     Ok(extensions)
 }
 
-/// Emits an extension update to the main window.
+/// Reads the manifest of the installed extension `id`
+pub fn read_manifest(extensions_path: &Path, id: &str) -> error::Result<ExtensionManifest> {
+    let manifest_data = fs::read_to_string(extensions_path.join(id).join("manifest.json"))?;
+    Ok(serde_json::from_str(&manifest_data)?)
+}
+
+/// Emits the entries of the active wheel to the main window.
 pub fn emit_extensions_update(app: &AppHandle) -> error::Result<()> {
     let app_state = app.state::<AppState>();
-    let extensions = get_installed_extensions(app_state)?;
+    let entries = get_wheel_entries(app_state)?;
 
     if let Some(main_window) = app.get_webview_window("main") {
-        main_window.emit("update-extensions", extensions)?;
+        main_window.emit("update-extensions", entries)?;
     }
 
     Ok(())
@@ -151,29 +299,79 @@ pub async fn download_extension_latest(latest_url: &String) -> error::Result<Lat
     Ok(latest)
 }
 
-/// Download extension assets
-pub async fn download_extension(extension_latest: &Latest) -> error::Result<Vec<u8>> {
-    let key = current_platform_key();
+/// Download the release `version` of the extension at `latest_url`
+pub async fn download_extension_release(
+    latest_url: &String,
+    version: &Version,
+) -> error::Result<Release> {
+    let latest = download_extension_latest(latest_url).await?;
+    if &latest.version == version {
+        return Ok(Release {
+            version: latest.version,
+            assets: latest.assets,
+        });
+    }
 
-    if let Some(asset) = extension_latest.assets.get(&key) {
-        // download the ZIP
-        let resp = reqwest::get(&asset.url).await?;
-        let bytes = resp.bytes().await?.to_vec();
+    let Some(releases_url) = latest.releases_url else {
+        return Err(Error::UnknownRelease(version.to_string()));
+    };
+    let resp = reqwest::get(releases_url).await?;
+    let releases: Vec<Release> = resp.json().await?;
 
-        // verify SHA-256 checksum
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        let sum = hex::encode(hasher.finalize());
-        if sum != asset.checksum {
-            return Err(Error::Checksum);
-        }
+    releases
+        .into_iter()
+        .find(|r| &r.version == version)
+        .ok_or_else(|| Error::UnknownRelease(version.to_string()))
+}
 
-        Ok(bytes)
+/// Download the asset for the current platform out of `assets`
+pub async fn download_extension(assets: &HashMap<String, Asset>) -> error::Result<Vec<u8>> {
+    if let Some(asset) = asset_keys().iter().find_map(|key| assets.get(key)) {
+        download_asset(asset).await
     } else {
         Err(Error::NoAssets)
     }
 }
 
+/// Download a single asset, verifying its checksum
+pub async fn download_asset(asset: &Asset) -> error::Result<Vec<u8>> {
+    // download the ZIP
+    let resp = reqwest::get(&asset.url).await?;
+    let bytes = resp.bytes().await?.to_vec();
+
+    // verify SHA-256 checksum
+    if sha256_hex(&bytes) != asset.checksum {
+        return Err(Error::Checksum);
+    }
+
+    Ok(bytes)
+}
+
+/// Returns the hex encoded SHA-256 checksum of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex::encode(hasher.finalize())
+}
+
+/// Returns the checksum of the asset an extension was installed from, if recorded
+pub fn installed_checksum(extensions_path: &Path, id: &str) -> Option<String> {
+    fs::read_to_string(extensions_path.join(id).join(CHECKSUM_FILE)).ok()
+}
+
+/// Extension ids name their directory, so only plain ones are accepted
+pub fn valid_id(id: &str) -> error::Result<String> {
+    let valid = !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(Error::InvalidId(id.to_string()));
+    }
+    Ok(id.to_string())
+}
+
 /// Returns the current platform
 pub fn current_platform_key() -> String {
     let raw_os = std::env::consts::OS;
@@ -185,12 +383,18 @@ pub fn current_platform_key() -> String {
     format!("{}-{}", os, arch)
 }
 
+/// Keys of the assets running on the current platform, the one built for it first
+pub fn asset_keys() -> [String; 2] {
+    [current_platform_key(), WASM_ASSET_KEY.to_string()]
+}
+
 /// Install downloaded `bytes` to extensions folder by `extension_id`
 pub async fn install_extension(
     id: &String,
     bytes: Vec<u8>,
     app_state: State<'_, AppState>,
 ) -> error::Result<()> {
+    valid_id(id)?;
     let extension_directory = app_state.extensions_path.join(id);
     let tmp = std::env::temp_dir().join(format!("{id}.tar.gz"));
 
@@ -208,7 +412,10 @@ pub async fn install_extension(
     // unzip
     let tar_gz = File::open(&tmp)?;
     let dec = GzDecoder::new(tar_gz);
-    Archive::new(dec).unpack(extension_directory)?;
+    Archive::new(dec).unpack(&extension_directory)?;
+
+    // remember where it came from
+    fs::write(extension_directory.join(CHECKSUM_FILE), sha256_hex(&bytes))?;
 
     // cleanup
     fs::remove_file(&tmp)?;
@@ -217,26 +424,48 @@ pub async fn install_extension(
 
 /// Delete extension by `extension_id`
 #[tauri::command]
-pub async fn delete_extension(id: String, app: AppHandle) -> error::Result<()> {
-    let app_state = app.state::<AppState>();
+pub async fn delete_extension(id: String, keep_data: bool, app: AppHandle) -> error::Result<()> {
+    remove_extension(&id, keep_data, &app.state::<AppState>())?;
 
-    let extension_directory = app_state.extensions_path.join(&id);
+    emit_extensions_update(&app)?;
+
+    Ok(())
+}
+
+/// Removes the files of extension `id` and all references to it from the config.
+/// Its own data is kept if `keep_data` is set, e.g. for installing it again later.
+pub fn remove_extension(id: &String, keep_data: bool, app_state: &AppState) -> error::Result<()> {
+    valid_id(id)?;
+    let extension_directory = app_state.extensions_path.join(id);
     if extension_directory.exists() {
         fs::remove_dir_all(&extension_directory)?;
     }
+    logs::remove(id)?;
+    // Installing it again asks for them again
+    app_state.permissions.set(id, Vec::new())?;
 
-    // Remove from config
-    {
-        let mut config = app_state.config.write()?;
-
-        config.enabled.retain(|f| f != &id);
-        config.ordered.retain(|f| f != &id);
-
-        // persist changes
-        fs::write(&app_state.config_path, serde_json::to_string(&*config)?)?;
+    let data_directory = app_state.extension_data_path.join(id);
+    if !keep_data && data_directory.exists() {
+        fs::remove_dir_all(&data_directory)?;
     }
 
-    emit_extensions_update(&app)?;
+    // Remove from config
+    app_state.config.update(|config| {
+        for profile in config.profiles.values_mut() {
+            profile.enabled.retain(|f| f != id);
+            profile.ordered.retain(|f| f != id);
+            profile.hotkeys.remove(id);
+            for wheel in profile.wheels.values_mut() {
+                wheel.extensions.retain(|f| f != id);
+            }
+            for group in profile.groups.values_mut() {
+                group.entries.retain(|f| f != id);
+            }
+        }
+        config.limits.remove(id);
+        config.quarantined.remove(id);
+        Ok(())
+    })?;
 
     Ok(())
 }
@@ -251,7 +480,7 @@ pub async fn download_and_install_extension(
     let app_state = app.state::<AppState>();
 
     let latest = download_extension_latest(&latest_url).await?;
-    let bytes = download_extension(&latest).await?;
+    let bytes = download_extension(&latest.assets).await?;
     install_extension(&id, bytes, app_state.clone()).await?;
 
     // Emit update
@@ -259,19 +488,101 @@ pub async fn download_and_install_extension(
 
     // Return newly installed extension
     let extensions_path = app_state.extensions_path.clone();
-    let config = app_state.config.read()?.clone();
-    let enabled = config.enabled;
+    let enabled = app_state.config.read()?.profile().enabled.clone();
 
-    let manifest_path = extensions_path.join(&id).join("manifest.json");
-    let manifest_data = std::fs::read_to_string(&manifest_path)?;
-    let manifest: ExtensionManifest = serde_json::from_str(&manifest_data)?;
+    let manifest = read_manifest(&extensions_path, &id)?;
+    let granted = app_state.permissions.granted(&id)?;
 
     let icon_path = extensions_path.join(&id).join("icon.svg");
     let this_enabled = enabled.contains(&id);
 
     Ok(InstalledExtensionInfo {
+        pending_permissions: pending(&manifest.permissions, &granted),
         manifest,
         icon_path,
         enabled: this_enabled,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(id: &str) -> InstalledExtensionInfo {
+        InstalledExtensionInfo {
+            manifest: ExtensionManifest {
+                id: id.into(),
+                name: id.into(),
+                author: "pointy".into(),
+                version: "0.1.0".parse().unwrap(),
+                description: String::new(),
+                latest_url: String::new(),
+                keywords: Vec::new(),
+                permissions: Vec::new(),
+                kind: Default::default(),
+            },
+            icon_path: PathBuf::new(),
+            enabled: false,
+            pending_permissions: Vec::new(),
+        }
+    }
+
+    fn group(entries: &[&str]) -> Group {
+        Group {
+            name: "Group".into(),
+            entries: entries.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    /// Ids of the entries, with those of groups in brackets
+    fn tree(entries: &[WheelEntry]) -> String {
+        entries
+            .iter()
+            .map(|entry| match entry {
+                WheelEntry::Extension(e) => e.manifest.id.clone(),
+                WheelEntry::Group(g) => format!("{}[{}]", g.id, tree(&g.entries)),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn resolve(ids: &[&str], groups: &BTreeMap<String, Group>) -> Vec<WheelEntry> {
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        let installed = [installed("math"), installed("qrcode"), installed("text")];
+        resolve_entries(&ids, groups, &installed, &mut Vec::new())
+    }
+
+    #[test]
+    fn groups_are_resolved_into_a_tree() {
+        let groups = BTreeMap::from([
+            ("tools".into(), group(&["qrcode", "nested", "missing"])),
+            ("nested".into(), group(&["text"])),
+        ]);
+        let entries = resolve(&["math", "tools", "uninstalled"], &groups);
+
+        assert_eq!(tree(&entries), "math tools[qrcode nested[text]]");
+        // Everything shown in a wheel counts as enabled
+        let WheelEntry::Extension(math) = &entries[0] else {
+            panic!("math is an extension");
+        };
+        assert!(math.enabled);
+    }
+
+    #[test]
+    fn group_cycles_are_cut() {
+        let groups = BTreeMap::from([
+            ("a".into(), group(&["math", "b"])),
+            ("b".into(), group(&["a", "qrcode"])),
+            ("self".into(), group(&["self", "text"])),
+        ]);
+
+        assert_eq!(tree(&resolve(&["a"], &groups)), "a[math b[qrcode]]");
+        assert_eq!(tree(&resolve(&["b"], &groups)), "b[a[math] qrcode]");
+        assert_eq!(tree(&resolve(&["self"], &groups)), "self[text]");
+        // The same group may appear twice as long as it doesn't contain itself
+        assert_eq!(
+            tree(&resolve(&["b", "b"], &groups)),
+            "b[a[math] qrcode] b[a[math] qrcode]"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+/// A rectangle in physical pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether `point` lies within the rectangle, including its top and left edge
+    pub fn contains(&self, (x, y): (f64, f64)) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// The centre of the rectangle
+    pub fn centre(&self) -> (f64, f64) {
+        (self.x + self.width / 2., self.y + self.height / 2.)
+    }
+
+    /// Squared distance from `point` to the closest point of the rectangle
+    fn distance_sq(&self, (x, y): (f64, f64)) -> f64 {
+        let dx = (self.x - x).max(x - (self.x + self.width)).max(0.);
+        let dy = (self.y - y).max(y - (self.y + self.height)).max(0.);
+        dx * dx + dy * dy
+    }
+}
+
+/// The parts of a monitor relevant for placing the wheel, all in physical pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MonitorArea {
+    pub bounds: Rect,
+    /// Part of the monitor not covered by task bars, docks and the like
+    pub work_area: Rect,
+    pub scale_factor: f64,
+}
+
+/// Returns the monitor under `point`, or the closest one if the point lies in a gap between them
+pub fn monitor_at(monitors: &[MonitorArea], point: (f64, f64)) -> Option<&MonitorArea> {
+    monitors
+        .iter()
+        .find(|m| m.bounds.contains(point))
+        .or_else(|| {
+            monitors.iter().min_by(|a, b| {
+                a.bounds
+                    .distance_sq(point)
+                    .total_cmp(&b.bounds.distance_sq(point))
+            })
+        })
+}
+
+/// Returns the physical top-left position of a window of `logical_size` centred at `point`.
+/// The window is scaled by the monitor's scale factor and kept within its work area.
+pub fn wheel_position(
+    point: (f64, f64),
+    logical_size: (f64, f64),
+    monitor: &MonitorArea,
+) -> (f64, f64) {
+    let width = logical_size.0 * monitor.scale_factor;
+    let height = logical_size.1 * monitor.scale_factor;
+
+    (
+        clamp_span(
+            point.0 - width / 2.,
+            width,
+            monitor.work_area.x,
+            monitor.work_area.width,
+        ),
+        clamp_span(
+            point.1 - height / 2.,
+            height,
+            monitor.work_area.y,
+            monitor.work_area.height,
+        ),
+    )
+}
+
+/// Moves the span starting at `start` into the area, preferring its start if it doesn't fit
+fn clamp_span(start: f64, length: f64, area_start: f64, area_length: f64) -> f64 {
+    start.min(area_start + area_length - length).max(area_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: f64, y: f64, width: f64, height: f64, scale_factor: f64) -> MonitorArea {
+        MonitorArea {
+            bounds: Rect::new(x, y, width, height),
+            work_area: Rect::new(x, y, width, height),
+            scale_factor,
+        }
+    }
+
+    #[test]
+    fn finds_monitor_under_point() {
+        let monitors = [
+            monitor(0., 0., 1920., 1080., 1.),
+            monitor(1920., 0., 3840., 2160., 2.),
+        ];
+
+        assert_eq!(monitor_at(&monitors, (100., 100.)), Some(&monitors[0]));
+        assert_eq!(monitor_at(&monitors, (1920., 500.)), Some(&monitors[1]));
+        assert_eq!(monitor_at(&monitors, (5000., 2000.)), Some(&monitors[1]));
+    }
+
+    #[test]
+    fn finds_closest_monitor_in_gaps() {
+        let monitors = [
+            monitor(0., 0., 1920., 1080., 1.),
+            monitor(1920., -500., 1080., 1920., 1.),
+        ];
+
+        // Below the first monitor
+        assert_eq!(monitor_at(&monitors, (100., 1200.)), Some(&monitors[0]));
+        // Above the first monitor, next to the rotated one
+        assert_eq!(monitor_at(&monitors, (1900., -300.)), Some(&monitors[1]));
+        assert_eq!(monitor_at(&[], (0., 0.)), None);
+    }
+
+    #[test]
+    fn centres_window_on_point() {
+        let m = monitor(0., 0., 1920., 1080., 1.);
+        assert_eq!(wheel_position((500., 400.), (200., 200.), &m), (400., 300.));
+    }
+
+    #[test]
+    fn centres_window_on_monitor() {
+        let m = MonitorArea {
+            bounds: Rect::new(1920., 0., 2560., 1440.),
+            work_area: Rect::new(1920., 40., 2560., 1400.),
+            scale_factor: 1.,
+        };
+        let centre = m.work_area.centre();
+        assert_eq!(centre, (3200., 740.));
+        assert_eq!(wheel_position(centre, (500., 360.), &m), (2950., 560.));
+    }
+
+    #[test]
+    fn scales_window_by_monitor() {
+        let m = monitor(1920., 0., 3840., 2160., 2.);
+        assert_eq!(
+            wheel_position((3000., 1000.), (200., 200.), &m),
+            (2800., 800.)
+        );
+    }
+
+    #[test]
+    fn clamps_window_to_work_area() {
+        let m = MonitorArea {
+            bounds: Rect::new(0., 0., 1920., 1080.),
+            work_area: Rect::new(0., 25., 1920., 1000.),
+            scale_factor: 1.,
+        };
+
+        assert_eq!(wheel_position((10., 10.), (200., 200.), &m), (0., 25.));
+        assert_eq!(
+            wheel_position((1915., 1075.), (200., 200.), &m),
+            (1720., 825.)
+        );
+    }
+
+    #[test]
+    fn clamps_window_on_negative_monitor() {
+        let m = monitor(-2560., -200., 2560., 1440., 1.25);
+        assert_eq!(
+            wheel_position((-2550., -190.), (200., 200.), &m),
+            (-2560., -200.)
+        );
+    }
+
+    #[test]
+    fn oversized_window_starts_at_work_area() {
+        let m = monitor(0., 0., 300., 300., 2.);
+        assert_eq!(wheel_position((150., 150.), (200., 200.), &m), (0., 0.));
+    }
+}
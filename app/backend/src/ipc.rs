@@ -0,0 +1,241 @@
+//! Local socket for controlling pointy from outside, e.g. from scripts, the `pointyctl` CLI
+//! or a compositor keybinding on Wayland where global shortcuts aren't available.
+//!
+//! See [`pointy_ipc`] for the protocol, the methods are dispatched in [`handle`].
+
+use std::thread;
+
+use pointy_ipc::RpcError;
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tracing::info;
+
+use crate::{
+    config::{change_config, get_config, Config},
+    error::{self, Error},
+    extensions::{download_and_install_extension, get_installed_extensions},
+    launch::{self, LaunchAction},
+    runs::{self, cancel_extension_run, RunId},
+    update::update_extensions,
+    wheel, AppState,
+};
+
+/// Listens for requests on the socket in a background thread
+pub fn listen(app: &AppHandle) -> error::Result<()> {
+    let path = pointy_ipc::socket_path();
+    let listener = pointy_ipc::bind(&path)?;
+    info!(path = %path.display(), "listening for ipc requests");
+
+    let app = app.clone();
+    thread::spawn(move || {
+        pointy_ipc::serve(listener, move |method, params| handle(&app, method, params))
+    });
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct LaunchParams {
+    args: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct RunParams {
+    run_id: RunId,
+}
+
+#[derive(Deserialize)]
+struct InstallParams {
+    id: String,
+    latest_url: String,
+}
+
+#[derive(Deserialize)]
+struct ConfigParams {
+    config: Config,
+}
+
+/// A method with its parameters, checked before anything is done
+#[derive(Debug)]
+enum Call {
+    /// `toggle`, `show` or `hide`
+    Wheel(String),
+    Launch(LaunchAction),
+    RunExtension(String),
+    CancelExtensionRun(RunId),
+    ListExtensions,
+    InstallExtension {
+        id: String,
+        latest_url: String,
+    },
+    UpdateExtensions,
+    GetConfig,
+    ChangeConfig(Box<Config>),
+}
+
+/// Calls the command behind `method`, the same ones the frontend uses
+pub fn handle(app: &AppHandle, method: &str, params: Value) -> Result<Value, RpcError> {
+    execute(app, parse(method, params)?)
+}
+
+fn parse(method: &str, params: Value) -> Result<Call, RpcError> {
+    Ok(match method {
+        "toggle" | "show" | "hide" => Call::Wheel(method.to_string()),
+        "launch" => {
+            let LaunchParams { args } = RpcError::params(params)?;
+            Call::Launch(LaunchAction::parse(&args)?)
+        }
+        "run_extension" => {
+            let IdParams { id } = RpcError::params(params)?;
+            Call::RunExtension(id)
+        }
+        "cancel_extension_run" => {
+            let RunParams { run_id } = RpcError::params(params)?;
+            Call::CancelExtensionRun(run_id)
+        }
+        "list_extensions" => Call::ListExtensions,
+        "install_extension" => {
+            let InstallParams { id, latest_url } = RpcError::params(params)?;
+            Call::InstallExtension { id, latest_url }
+        }
+        "update_extensions" => Call::UpdateExtensions,
+        "get_config" => Call::GetConfig,
+        "change_config" => {
+            let ConfigParams { config } = RpcError::params(params)?;
+            Call::ChangeConfig(Box::new(config))
+        }
+        _ => return Err(RpcError::method_not_found(method)),
+    })
+}
+
+fn execute(app: &AppHandle, call: Call) -> Result<Value, RpcError> {
+    match call {
+        Call::Wheel(method) => respond(wheel_command(app, &method)),
+        Call::Launch(action) => {
+            respond(tauri::async_runtime::block_on(launch::handle(app, action)))
+        }
+        // Waits for the run, so scripts can rely on its outcome
+        Call::RunExtension(id) => {
+            let (_, outcome) = runs::start(app, id)?;
+            respond(
+                outcome
+                    .recv()
+                    .unwrap_or_else(|_| Err(Error::Run("extension run was aborted".into()))),
+            )
+        }
+        Call::CancelExtensionRun(run_id) => respond(cancel_extension_run(run_id, app.state())),
+        Call::ListExtensions => respond(get_installed_extensions(app.state())),
+        Call::InstallExtension { id, latest_url } => respond(tauri::async_runtime::block_on(
+            download_and_install_extension(id, latest_url, app.clone()),
+        )),
+        Call::UpdateExtensions => respond(tauri::async_runtime::block_on(update_extensions(
+            app.clone(),
+        ))),
+        Call::GetConfig => respond(get_config(app.state())),
+        Call::ChangeConfig(config) => respond(change_config(*config, app.clone(), app.state())),
+    }
+}
+
+fn wheel_command(app: &AppHandle, method: &str) -> error::Result<()> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| Error::Tauri("main window is missing".into()))?;
+
+    match method {
+        "toggle" if window.is_visible()? => {
+            let activation = app.state::<AppState>().config.read()?.activation.clone();
+            wheel::select(app, &window, &activation)
+        }
+        "hide" => wheel::cancel(app, &window),
+        _ => wheel::show(app, &window, None),
+    }
+}
+
+fn respond<T: serde::Serialize>(result: error::Result<T>) -> Result<Value, RpcError> {
+    let value = result?;
+    serde_json::to_value(value).map_err(|e| RpcError::new(RpcError::INTERNAL_ERROR, e.to_string()))
+}
+
+impl From<Error> for RpcError {
+    fn from(err: Error) -> Self {
+        RpcError {
+            data: serde_json::to_value(&err).ok(),
+            ..RpcError::new(RpcError::APPLICATION_ERROR, err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use pointy_ipc::{Client, ClientError};
+    use serde_json::json;
+
+    use super::*;
+
+    fn code(result: Result<Call, RpcError>) -> i64 {
+        result.unwrap_err().code
+    }
+
+    #[test]
+    fn methods_are_parsed_with_their_params() {
+        assert!(matches!(parse("toggle", Value::Null), Ok(Call::Wheel(m)) if m == "toggle"));
+        assert!(matches!(
+            parse("run_extension", json!({ "id": "math" })),
+            Ok(Call::RunExtension(id)) if id == "math"
+        ));
+        assert!(matches!(
+            parse("cancel_extension_run", json!({ "run_id": 7 })),
+            Ok(Call::CancelExtensionRun(7))
+        ));
+        assert!(matches!(
+            parse("launch", json!({ "args": ["--install", "math"] })),
+            Ok(Call::Launch(LaunchAction::Install { id, registry: None })) if id == "math"
+        ));
+
+        assert_eq!(
+            code(parse("run_extension", json!({}))),
+            RpcError::INVALID_PARAMS
+        );
+        assert_eq!(
+            code(parse("explode", Value::Null)),
+            RpcError::METHOD_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn errors_of_the_backend_reach_the_client() {
+        let path = env::temp_dir().join(format!("pointy-ipc-handle-{}.sock", std::process::id()));
+        let listener = pointy_ipc::bind(&path).unwrap();
+        thread::spawn(move || {
+            pointy_ipc::serve(listener, |method, params| {
+                parse(method, params).map(|call| json!(format!("{call:?}")))
+            })
+        });
+
+        let mut client = Client::connect(&path).unwrap();
+        assert_eq!(
+            client.call("list_extensions", Value::Null).unwrap(),
+            json!("ListExtensions")
+        );
+
+        let Err(ClientError::Rpc(e)) =
+            client.call("launch", json!({ "args": ["--install", "../x"] }))
+        else {
+            panic!("invalid ids have to be rejected");
+        };
+        assert_eq!(e.code, RpcError::APPLICATION_ERROR);
+        let error: Error = serde_json::from_value(e.data.unwrap()).unwrap();
+        assert!(matches!(error, Error::InvalidId(id) if id == "../x"));
+
+        let _ = std::fs::remove_file(pointy_ipc::token_path(&path));
+        let _ = std::fs::remove_file(path);
+    }
+}
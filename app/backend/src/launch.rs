@@ -0,0 +1,180 @@
+//! Actions requested by the command line arguments of a launch or by `pointy://` links.
+//! A second instance forwards its arguments to the running one and exits.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tracing::warn;
+
+use crate::{
+    error::{self, Error},
+    extensions::{fetch_registry, valid_id},
+    tray::show_settings,
+    AppState,
+};
+
+/// Passed by autostart, so starting with the system doesn't open any window
+pub const AUTOSTART_ARG: &str = "--autostart";
+/// Scheme of links like `pointy://install?id=generate_qrcode`
+pub const DEEP_LINK_SCHEME: &str = "pointy";
+
+/// What a launch of the app asks for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LaunchAction {
+    /// Stay in the tray
+    Background,
+    OpenSettings,
+    /// Offer to install the extension `id` in the settings.
+    /// The registry, if given, has to be the configured one.
+    Install {
+        id: String,
+        registry: Option<String>,
+    },
+}
+
+impl LaunchAction {
+    /// Parses the arguments, without the program name.
+    /// Without any, e.g. when started again from the app menu, the settings are opened.
+    pub fn parse(args: &[String]) -> error::Result<Self> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [] | ["--settings"] => Ok(LaunchAction::OpenSettings),
+            [AUTOSTART_ARG] => Ok(LaunchAction::Background),
+            ["--install", id] => Ok(LaunchAction::Install {
+                id: valid_id(id)?,
+                registry: None,
+            }),
+            // Linux and Windows pass links as an argument
+            [link] if link.starts_with(&format!("{DEEP_LINK_SCHEME}:")) => {
+                Self::from_deep_link(link)
+            }
+            _ => Err(Error::Launch(format!(
+                "unknown arguments '{}'",
+                args.join(" ")
+            ))),
+        }
+    }
+
+    /// Parses a link like `pointy://install?id=generate_qrcode&registry=https://...`.
+    /// Anything but the known parameters is rejected.
+    pub fn from_deep_link(link: &str) -> error::Result<Self> {
+        let url = Url::parse(link).map_err(|e| Error::Launch(format!("malformed link: {e}")))?;
+        if url.scheme() != DEEP_LINK_SCHEME
+            || url.host_str() != Some("install")
+            || !url.username().is_empty()
+            || url.port().is_some()
+            || !matches!(url.path(), "" | "/")
+            || url.fragment().is_some()
+        {
+            return Err(Error::Launch(format!("unknown link '{link}'")));
+        }
+
+        let (mut id, mut registry) = (None, None);
+        for (key, value) in url.query_pairs() {
+            let param = match key.as_ref() {
+                "id" => &mut id,
+                "registry" => &mut registry,
+                _ => return Err(Error::Launch(format!("unknown link parameter '{key}'"))),
+            };
+            if param.replace(value.into_owned()).is_some() {
+                return Err(Error::Launch(format!("duplicate link parameter '{key}'")));
+            }
+        }
+
+        let id = id.ok_or_else(|| Error::Launch("link is missing the extension id".into()))?;
+        Ok(LaunchAction::Install {
+            id: valid_id(&id)?,
+            registry,
+        })
+    }
+}
+
+/// Performs `action` in the running instance
+pub async fn handle(app: &AppHandle, action: LaunchAction) -> error::Result<()> {
+    match action {
+        LaunchAction::Background => {}
+        LaunchAction::OpenSettings => show_settings(app)?,
+        LaunchAction::Install { id, registry } => {
+            let configured = app.state::<AppState>().config.read()?.registry.clone();
+            if let Some(registry) = registry.filter(|r| *r != configured) {
+                return Err(Error::Launch(format!(
+                    "registry '{registry}' is not the configured one"
+                )));
+            }
+
+            let extension = fetch_registry(&configured)
+                .await?
+                .into_iter()
+                .find(|e| e.id == id)
+                .ok_or_else(|| Error::Launch(format!("extension '{id}' is not in the registry")))?;
+
+            // Installed once confirmed in the settings
+            show_settings(app)?;
+            app.emit_to("settings", "install-extension", extension)?;
+        }
+    }
+    Ok(())
+}
+
+/// Handles `action` in the background, as looking up extensions takes a while
+pub fn spawn(app: &AppHandle, action: error::Result<LaunchAction>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match action {
+            Ok(action) => handle(&app, action).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!(error = %e, "failed to handle launch action");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(id: &str, registry: Option<&str>) -> LaunchAction {
+        LaunchAction::Install {
+            id: id.into(),
+            registry: registry.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn parses_install_links() {
+        assert_eq!(
+            LaunchAction::from_deep_link("pointy://install?id=generate_qrcode").unwrap(),
+            install("generate_qrcode", None)
+        );
+        assert_eq!(
+            LaunchAction::from_deep_link(
+                "pointy://install/?registry=https%3A%2F%2Fexample.com%2Fextensions.json&id=math"
+            )
+            .unwrap(),
+            install("math", Some("https://example.com/extensions.json"))
+        );
+        assert_eq!(
+            LaunchAction::parse(&["pointy://install?id=math".into()]).unwrap(),
+            install("math", None)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        for link in [
+            "pointy:install?id=math",
+            "pointy://uninstall?id=math",
+            "pointy://install/math?id=math",
+            "pointy://install",
+            "pointy://install?id=",
+            "pointy://install?id=math&id=text_metadata",
+            "pointy://install?id=math&run=true",
+            "pointy://install?id=..%2F..%2Fetc",
+            "pointy://install?id=math#fragment",
+            "pointy://user@install?id=math",
+            "https://install?id=math",
+        ] {
+            assert!(LaunchAction::from_deep_link(link).is_err(), "{link}");
+        }
+    }
+}
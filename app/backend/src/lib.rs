@@ -1,42 +1,79 @@
+pub mod backup;
 pub mod config;
 pub mod error;
 pub mod extensions;
+pub mod geometry;
+#[cfg(unix)]
+pub mod ipc;
+pub mod launch;
+pub mod lockfile;
+pub mod logs;
+pub mod palette;
+pub mod permissions;
+pub mod profiles;
+pub mod runs;
+pub mod shortcuts;
+pub mod tray;
 pub mod update;
+pub mod wheel;
 
-use std::{ffi::CString, fs, path::PathBuf, str::FromStr, sync::RwLock};
+use std::{fs, path::PathBuf, sync::Mutex};
 
-use config::{change_config, get_config, load_config, set_autolaunch, Config};
-use error::Error;
+use backup::{export_setup, import_setup};
+use config::{
+    apply_autolaunch, change_config, get_config, move_extension, set_activation, set_autolaunch,
+    set_extension_enabled, set_extension_shortcut, set_group, set_palette_shortcut, set_shortcut,
+    set_wheel, ActivationMode, Config, ConfigStore,
+};
 use extensions::{
     delete_extension, download_and_install_extension, fetch_online_extensions,
-    get_installed_extensions,
-};
-use libloading::{Library, Symbol};
-use pointy_api::device_query::{DeviceQuery, DeviceState};
-use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
-    tray::TrayIconBuilder,
-    Emitter, LogicalPosition, LogicalSize, Manager, State, WindowEvent,
+    get_installed_extensions, get_wheel_entries,
 };
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
-use tracing::info;
+use launch::{LaunchAction, AUTOSTART_ARG};
+use lockfile::{lockfile_drift, sync_lockfile, write_lockfile};
+use logs::get_extension_logs;
+use palette::{search_palette, UsageStore};
+use permissions::{grant_permissions, PermissionStore};
+use profiles::{create_profile, delete_profile, rename_profile, switch_profile};
+use runs::{cancel_extension_run, clear_run_records, get_run_records, RunId, Runs};
+use shortcuts::Action;
+use tauri::{AppHandle, Listener, Manager, WindowEvent};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_global_shortcut::ShortcutState;
+use tracing::{info, warn};
 use update::{update_app, update_extensions};
+use wheel::{close_group, open_group, select_slot, WheelState};
 
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 pub const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub struct AppState {
-    pub config_path: PathBuf,
     pub extensions_path: PathBuf,
-    pub config: RwLock<Config>,
+    /// Contains a directory per extension for its own data, see `pointy_api::data_dir`
+    pub extension_data_path: PathBuf,
+    pub config: ConfigStore,
+    pub wheel: Mutex<WheelState>,
+    pub usage: UsageStore,
+    pub permissions: PermissionStore,
+    pub runs: Runs,
 }
 
 impl AppState {
-    pub fn new(config_path: PathBuf, extensions_path: PathBuf, config: Config) -> Self {
+    pub fn new(
+        extensions_path: PathBuf,
+        extension_data_path: PathBuf,
+        config: ConfigStore,
+        usage: UsageStore,
+        permissions: PermissionStore,
+    ) -> Self {
         Self {
-            config_path,
             extensions_path,
-            config: RwLock::new(config),
+            extension_data_path,
+            config,
+            wheel: Mutex::new(WheelState::default()),
+            usage,
+            permissions,
+            runs: Runs::default(),
         }
     }
 }
@@ -47,49 +84,11 @@ fn get_version() -> &'static str {
     PKG_VERSION
 }
 
-/// Runs a specified extension. It loads the appropriate dynamic library
-/// for the current OS from the extension directory and calls the exported function.
-/// The function is assumed to be:
-///
-///   pub extern "C" fn run() -> *mut c_char
-///
+/// Starts a run of an extension on the worker pool and returns its id.
+/// Its progress and outcome are emitted as `run-update` events.
 #[tauri::command]
-fn run_extension(extension_name: String, app_state: State<'_, AppState>) -> error::Result<()> {
-    #[cfg(target_os = "windows")]
-    let lib_filename = "lib.dll";
-    #[cfg(target_os = "macos")]
-    let lib_filename = "lib.dylib";
-    #[cfg(target_os = "linux")]
-    let lib_filename = "lib.so";
-
-    let extensions_path = app_state
-        .extensions_path
-        .join(&extension_name)
-        .join(lib_filename);
-
-    unsafe {
-        let lib = Library::new(&extensions_path)?;
-
-        let func: Symbol<unsafe extern "C" fn() -> *mut std::os::raw::c_char> =
-            lib.get(b"run\0")?;
-
-        let raw_ptr = func();
-        if raw_ptr.is_null() {
-            return Err(Error::LibLoading(
-                "Extension returned a null pointer".to_string(),
-            ));
-        }
-
-        let cstring = CString::from_raw(raw_ptr);
-        let result_str = cstring.into_string()?;
-
-        // As with the helper in `pointy_api`, if the string is empty no error occurred
-        if result_str.is_empty() {
-            Ok(())
-        } else {
-            Err(Error::LibLoading(result_str))
-        }
-    }
+fn run_extension(extension_name: String, app: AppHandle) -> error::Result<RunId> {
+    Ok(runs::start(&app, extension_name)?.0)
 }
 
 /// Reads the file of a certain path to string.
@@ -98,9 +97,20 @@ fn read_to_string(path: PathBuf) -> error::Result<String> {
     Ok(fs::read_to_string(path)?)
 }
 
+/// Returns the command line arguments, without the program name
+pub fn launch_args() -> Vec<String> {
+    std::env::args().skip(1).collect()
+}
+
 /// Starting point for desktop app
 pub fn run() {
     tauri::Builder::default()
+        // Registered first, so a second instance hands over its arguments before setting up
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            info!("handed over by a second instance");
+            let args = args.get(1..).unwrap_or_default();
+            launch::spawn(app, LaunchAction::parse(args));
+        }))
         .setup(|app| {
             info!("application setup starting");
 
@@ -111,23 +121,60 @@ pub fn run() {
             if !data_path.exists() {
                 fs::create_dir_all(&data_path)?;
             }
-            let config_path = data_path.join("config.json");
-            if !config_path.exists() {
-                fs::write(&config_path, serde_json::to_string(&Config::default())?)?;
-            }
             let extensions_path = data_path.join("extensions");
             if !extensions_path.exists() {
                 fs::create_dir_all(&extensions_path)?;
             }
+            let extension_data_path = data_path.join("extension_data");
+            if !extension_data_path.exists() {
+                fs::create_dir_all(&extension_data_path)?;
+            }
+            logs::init(data_path.join("logs"))?;
 
             // Initial App Config
-            let config = load_config(&config_path)?;
+            let config_store = ConfigStore::open(data_path.join("config.json"), handle.clone())?;
+            let config = config_store.read()?.clone();
+
+            // Save state, before any requests can come in
+            let usage = UsageStore::open(data_path.join("usage.json"));
+            let permissions = PermissionStore::open(data_path.join("permissions.json"));
+            app.manage(AppState::new(
+                extensions_path,
+                extension_data_path,
+                config_store,
+                usage,
+                permissions,
+            ));
+
+            // Control from outside, e.g. by scripts or a compositor keybinding
+            #[cfg(unix)]
+            if let Err(e) = ipc::listen(&handle) {
+                warn!(error = %e, "failed to listen on the control socket, use the hotkeys instead");
+            }
 
             // Main Window
             let main_window = handle.get_webview_window("main").unwrap();
-            let scale_factor = main_window
-                .current_monitor()?
-                .map_or(1., |f| f.scale_factor());
+
+            // Cancel the wheel on lost focus, unless it is held open
+            let main_window_copy = main_window.clone();
+            let focus_handle = handle.clone();
+            main_window.on_window_event(move |event| {
+                if let &WindowEvent::Focused(false) = event {
+                    let app_state = focus_handle.state::<AppState>();
+                    let mode = match app_state.config.read() {
+                        Ok(config) => config.activation.mode,
+                        Err(e) => {
+                            warn!(error = %e, "failed to read config on lost focus");
+                            return;
+                        }
+                    };
+                    if mode != ActivationMode::HoldRelease {
+                        if let Err(e) = wheel::cancel(&focus_handle, &main_window_copy) {
+                            warn!(error = %e, "failed to cancel wheel");
+                        }
+                    }
+                }
+            });
 
             // Close settings window on lost focus
             let settings_window = handle.get_webview_window("settings").unwrap();
@@ -142,110 +189,137 @@ pub fn run() {
             handle.plugin(
                 tauri_plugin_global_shortcut::Builder::new()
                     .with_handler(move |app, shortcut, event| {
-                        let current_window_shortcut_str = app
-                            .state::<AppState>()
-                            .config
-                            .read()
-                            .unwrap()
-                            .shortcut
-                            .clone();
-                        let current_window_shortcut =
-                            Shortcut::from_str(&current_window_shortcut_str).unwrap();
-
-                        if shortcut == &current_window_shortcut {
-                            match event.state() {
-                                ShortcutState::Pressed => {
-                                    // get mouse position
-                                    let device_state = DeviceState::new();
-                                    let pos = device_state.get_mouse().coords;
-
-                                    // get window size
-                                    let size: LogicalSize<u32> = LogicalSize::from_physical(
-                                        main_window.outer_size().unwrap(),
-                                        scale_factor,
-                                    );
-
-                                    // window size divided by 2 for centering relative to the mouse position
-                                    let logical_pos = LogicalPosition::new(
-                                        pos.0.saturating_sub((size.width / 2) as i32),
-                                        pos.1.saturating_sub((size.height / 2) as i32),
-                                    );
-
-                                    main_window.set_position(logical_pos).unwrap();
-                                    main_window.show().unwrap();
-                                    main_window.set_focus().unwrap();
+                        let app_state = app.state::<AppState>();
+                        let action = match app_state.config.read() {
+                            Ok(config) => shortcuts::action(&config, shortcut),
+                            Err(e) => {
+                                warn!(error = %e, "failed to read config for shortcut");
+                                return;
+                            }
+                        };
+
+                        match action {
+                            Some(Action::Wheel(wheel)) => {
+                                if let Err(e) =
+                                    wheel::on_shortcut(app, &main_window, wheel, event.state())
+                                {
+                                    warn!(error = %e, "failed to handle wheel shortcut");
+                                }
+                            }
+                            Some(Action::Palette) => {
+                                if let ShortcutState::Pressed = event.state() {
+                                    if let Err(e) = palette::show(app) {
+                                        warn!(error = %e, "failed to show palette");
+                                    }
                                 }
-                                ShortcutState::Released => {
-                                    main_window.hide().unwrap();
-                                    main_window.emit("select-option", ()).unwrap();
+                            }
+                            Some(Action::Extension(id)) => {
+                                if let ShortcutState::Pressed = event.state() {
+                                    if let Err(e) = runs::start(app, id.clone()) {
+                                        warn!(error = %e, id, "extension run failed");
+                                    }
                                 }
                             }
+                            None => {}
                         }
                     })
                     .build(),
             )?;
 
-            // Shortcut from Config
-            app.global_shortcut()
-                .register(Shortcut::from_str(&config.shortcut)?)?;
+            // Shortcuts from Config, not available everywhere (e.g. on Wayland)
+            if let Err(e) = shortcuts::register(&handle, &config) {
+                warn!(error = %e, "failed to register global shortcuts, use the ipc commands instead");
+            }
 
             // System Tray
-            let version = MenuItemBuilder::new(format!("{PKG_NAME} {PKG_VERSION}"))
-                .enabled(false)
-                .build(app)?;
-            let settings = MenuItemBuilder::new("Settings")
-                .id("settings")
-                .accelerator("CmdOrCtrl+,")
-                .build(app)?;
-            let menu = MenuBuilder::new(app)
-                .item(&version)
-                .separator()
-                .item(&settings)
-                .separator()
-                .quit()
-                .build()?;
-            TrayIconBuilder::with_id("main_tray")
-                .menu(&menu)
-                .icon(app.default_window_icon().unwrap().clone())
-                .on_menu_event({
-                    move |app, event| {
-                        if event.id.as_ref() == "settings" {
-                            if let Some(settings_window) = app.get_webview_window("settings") {
-                                settings_window.show().unwrap();
-                                settings_window.set_focus().unwrap();
-                                settings_window.emit("open-settings", ()).unwrap();
-                            }
-                        }
-                    }
-                })
-                .build(app)?;
+            tray::create(&handle, &config)?;
+
+            // Keep the profiles listed in the tray up to date
+            let tray_handle = handle.clone();
+            handle.listen_any("config-changed", move |event| {
+                let result = serde_json::from_str::<Config>(event.payload())
+                    .map_err(error::Error::from)
+                    .and_then(|config| Ok(tray::refresh(&tray_handle, &config)?));
+                if let Err(e) = result {
+                    warn!(error = %e, "failed to refresh tray menu");
+                }
+            });
 
             #[cfg(target_os = "macos")]
             app.set_activation_policy(tauri::ActivationPolicy::Accessory);
 
             // Manage autolaunch
-            handle.plugin(tauri_plugin_autostart::Builder::new().build())?;
-            set_autolaunch(&config, &handle)?;
+            handle.plugin(
+                tauri_plugin_autostart::Builder::new()
+                    .arg(AUTOSTART_ARG)
+                    .build(),
+            )?;
+            apply_autolaunch(&config, &handle)?;
 
-            // Save state
-            app.manage(AppState::new(config_path, extensions_path, config));
+            // Arguments of this launch, like `--install <id>` or a `pointy://` link
+            let args = launch_args();
+            if !args.is_empty() {
+                launch::spawn(&handle, LaunchAction::parse(&args));
+            }
+
+            // Links opened while running, on macOS they never arrive as arguments
+            #[cfg(any(target_os = "linux", windows))]
+            if let Err(e) = handle.deep_link().register_all() {
+                warn!(error = %e, "failed to register the link scheme");
+            }
+            let link_handle = handle.clone();
+            handle.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    launch::spawn(&link_handle, LaunchAction::from_deep_link(url.as_str()));
+                }
+            });
 
             info!("application is setup");
 
             Ok(())
         })
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
             get_version,
             get_installed_extensions,
+            get_wheel_entries,
+            open_group,
+            close_group,
+            select_slot,
+            search_palette,
             fetch_online_extensions,
             run_extension,
+            cancel_extension_run,
+            get_run_records,
+            clear_run_records,
+            get_extension_logs,
+            grant_permissions,
             download_and_install_extension,
             delete_extension,
             update_app,
             update_extensions,
             get_config,
             change_config,
+            set_extension_enabled,
+            move_extension,
+            set_shortcut,
+            set_extension_shortcut,
+            set_palette_shortcut,
+            set_wheel,
+            set_group,
+            set_activation,
+            set_autolaunch,
+            create_profile,
+            rename_profile,
+            delete_profile,
+            switch_profile,
+            export_setup,
+            import_setup,
+            write_lockfile,
+            lockfile_drift,
+            sync_lockfile,
             read_to_string
         ])
         .run(tauri::generate_context!())
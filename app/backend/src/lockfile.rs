@@ -0,0 +1,274 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tracing::info;
+
+use crate::{
+    error::{self, Error},
+    extensions::{
+        asset_keys, download_asset, download_extension_release, emit_extensions_update,
+        get_installed_extensions, install_extension, installed_checksum, remove_extension,
+        valid_id, Asset, ExtensionManifest,
+    },
+    AppState,
+};
+
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// A pinned set of extensions shared between machines, usually checked in as `pointy.lock`
+#[derive(Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    pub extensions: Vec<LockedExtension>,
+}
+
+/// An extension pinned to an exact release
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LockedExtension {
+    pub id: String,
+    pub version: Version,
+    pub latest_url: String,
+    /// Assets of the release by platform key, see [`asset_keys`].
+    /// Their urls name the version, so they keep pointing to the pinned build.
+    pub assets: BTreeMap<String, Asset>,
+}
+
+/// Differences between the installed extensions and a lockfile
+#[derive(Serialize, Default)]
+pub struct LockDrift {
+    /// Locked extensions which aren't installed
+    pub missing: Vec<String>,
+    /// Installed extensions whose version or checksum differs from the locked one
+    pub mismatched: Vec<String>,
+    /// Installed extensions which aren't locked
+    pub extra: Vec<String>,
+}
+
+/// Loads the lockfile at `path`, rejecting it before anything is installed if any id is invalid
+pub fn load_lockfile(path: &PathBuf) -> error::Result<Lockfile> {
+    let data = fs::read_to_string(path)?;
+    let lockfile: Lockfile = serde_json::from_str(&data)?;
+    if lockfile.version != LOCKFILE_VERSION {
+        return Err(Error::Lockfile(format!(
+            "unsupported version {}",
+            lockfile.version
+        )));
+    }
+    for extension in &lockfile.extensions {
+        valid_id(&extension.id)?;
+    }
+    Ok(lockfile)
+}
+
+/// Writes a lockfile to `path`, pinning the installed extensions at their current versions
+#[tauri::command]
+pub async fn write_lockfile(path: PathBuf, app: AppHandle) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+
+    let mut extensions = Vec::new();
+    for extension in get_installed_extensions(app_state)? {
+        let manifest = extension.manifest;
+        let release = download_extension_release(&manifest.latest_url, &manifest.version).await?;
+
+        extensions.push(LockedExtension {
+            id: manifest.id,
+            version: release.version,
+            latest_url: manifest.latest_url,
+            assets: release.assets.into_iter().collect(),
+        });
+    }
+    extensions.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let lockfile = Lockfile {
+        version: LOCKFILE_VERSION,
+        extensions,
+    };
+    fs::write(&path, serde_json::to_string_pretty(&lockfile)? + "\n")?;
+
+    info!(path = %path.display(), "wrote lockfile");
+    Ok(())
+}
+
+/// Compares the installed extensions against the lockfile at `path`
+#[tauri::command]
+pub fn lockfile_drift(path: PathBuf, app_state: State<'_, AppState>) -> error::Result<LockDrift> {
+    let lockfile = load_lockfile(&path)?;
+    installed_drift(&lockfile, &app_state)
+}
+
+/// Installs, re-pins and removes extensions until they match the lockfile at `path`.
+/// Returns the drift which was fixed.
+#[tauri::command]
+pub async fn sync_lockfile(path: PathBuf, app: AppHandle) -> error::Result<LockDrift> {
+    let app_state = app.state::<AppState>();
+
+    let lockfile = load_lockfile(&path)?;
+    let drift = installed_drift(&lockfile, &app_state)?;
+
+    let keys = asset_keys();
+    for extension in &lockfile.extensions {
+        if !drift.missing.contains(&extension.id) && !drift.mismatched.contains(&extension.id) {
+            continue;
+        }
+
+        let asset = keys
+            .iter()
+            .find_map(|key| extension.assets.get(key))
+            .ok_or(Error::NoAssets)?;
+        let bytes = download_asset(asset).await?;
+        install_extension(&extension.id, bytes, app_state.clone()).await?;
+        info!(id = %extension.id, version = %extension.version, "installed locked extension");
+    }
+
+    for id in &drift.extra {
+        // Its data is kept, as the lockfile may well bring it back
+        remove_extension(id, true, &app_state)?;
+        info!(id = %id, "removed extension missing in lockfile");
+    }
+
+    emit_extensions_update(&app)?;
+
+    Ok(drift)
+}
+
+/// The drift of the installed extensions from `lockfile`
+fn installed_drift(
+    lockfile: &Lockfile,
+    app_state: &State<'_, AppState>,
+) -> error::Result<LockDrift> {
+    let installed = get_installed_extensions(app_state.clone())?
+        .into_iter()
+        .map(|extension| {
+            let checksum = installed_checksum(&app_state.extensions_path, &extension.manifest.id);
+            (extension.manifest, checksum)
+        })
+        .collect::<Vec<_>>();
+    Ok(drift(lockfile, &installed, &asset_keys()))
+}
+
+/// Compares the `installed` extensions, with the checksum of their asset if recorded,
+/// to those locked for the platform `keys`
+fn drift(
+    lockfile: &Lockfile,
+    installed: &[(ExtensionManifest, Option<String>)],
+    keys: &[String],
+) -> LockDrift {
+    let mut drift = LockDrift::default();
+    for locked in &lockfile.extensions {
+        let Some((manifest, checksum)) = installed.iter().find(|(m, _)| m.id == locked.id) else {
+            drift.missing.push(locked.id.clone());
+            continue;
+        };
+
+        // Extensions installed by older versions have no recorded checksum
+        let checksum_differs = match (checksum, keys.iter().find_map(|key| locked.assets.get(key)))
+        {
+            (Some(checksum), Some(asset)) => checksum != &asset.checksum,
+            _ => false,
+        };
+
+        if manifest.version != locked.version || checksum_differs {
+            drift.mismatched.push(locked.id.clone());
+        }
+    }
+
+    drift.extra = installed
+        .iter()
+        .map(|(m, _)| m.id.clone())
+        .filter(|id| !lockfile.extensions.iter().any(|l| &l.id == id))
+        .collect();
+
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn manifest(id: &str, version: &str) -> ExtensionManifest {
+        ExtensionManifest {
+            id: id.into(),
+            name: id.into(),
+            author: "someone".into(),
+            version: version.parse().unwrap(),
+            description: String::new(),
+            latest_url: String::new(),
+            keywords: Vec::new(),
+            permissions: Vec::new(),
+            kind: Default::default(),
+        }
+    }
+
+    fn locked(id: &str, version: &str, assets: &[(&str, &str)]) -> LockedExtension {
+        LockedExtension {
+            id: id.into(),
+            version: version.parse().unwrap(),
+            latest_url: String::new(),
+            assets: assets
+                .iter()
+                .map(|(key, checksum)| {
+                    let asset = Asset {
+                        url: String::new(),
+                        checksum: checksum.to_string(),
+                    };
+                    (key.to_string(), asset)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn drift_is_detected() {
+        let lockfile = Lockfile {
+            version: LOCKFILE_VERSION,
+            extensions: vec![
+                locked("missing", "1.0.0", &[]),
+                locked("same", "1.0.0", &[("linux-x86_64", "aaa")]),
+                locked("older", "1.0.0", &[]),
+                locked("rebuilt", "1.0.0", &[("linux-x86_64", "aaa")]),
+                locked("unrecorded", "1.0.0", &[("linux-x86_64", "aaa")]),
+                // Only the asset of the platform counts
+                locked("wasm", "1.0.0", &[("linux-x86_64", "aaa"), ("wasm", "bbb")]),
+            ],
+        };
+        let installed = [
+            (manifest("same", "1.0.0"), Some("aaa".into())),
+            (manifest("older", "0.9.0"), None),
+            (manifest("rebuilt", "1.0.0"), Some("ccc".into())),
+            (manifest("unrecorded", "1.0.0"), None),
+            (manifest("wasm", "1.0.0"), Some("bbb".into())),
+            (manifest("extra", "1.0.0"), None),
+        ];
+        let keys = ["linux-x86_64".to_string(), "wasm".to_string()];
+
+        let drift = drift(&lockfile, &installed, &keys);
+        assert_eq!(drift.missing, ["missing"]);
+        assert_eq!(drift.mismatched, ["older", "rebuilt", "wasm"]);
+        assert_eq!(drift.extra, ["extra"]);
+    }
+
+    #[test]
+    fn invalid_lockfiles_are_rejected() {
+        let path = env::temp_dir().join(format!("pointy-{}.lock", std::process::id()));
+        let load = |lockfile: serde_json::Value| {
+            fs::write(&path, lockfile.to_string()).unwrap();
+            load_lockfile(&path)
+        };
+        let extension = |id: &str| serde_json::json!({ "id": id, "version": "1.0.0", "latest_url": "", "assets": {} });
+
+        let lockfile = load(serde_json::json!({ "version": 1, "extensions": [extension("ok")] }));
+        assert!(lockfile.is_ok());
+        let lockfile = load(serde_json::json!({ "version": 2, "extensions": [] }));
+        assert!(matches!(lockfile, Err(Error::Lockfile(_))));
+        for id in ["../escape", "", "a/b", "."] {
+            let lockfile = load(serde_json::json!({ "version": 1, "extensions": [extension(id)] }));
+            assert!(matches!(lockfile, Err(Error::InvalidId(_))), "{id}");
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+//! Logs of extensions, written to a rotating file per extension besides the usual output.
+//!
+//! Every run enters an [`EXTENSION_SPAN`] with the extension id, all events within it,
+//! including those forwarded by `pointy_api::info!` and friends, go to `logs/<id>.log`.
+
+use std::{
+    fmt::{self, Write as _},
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    fmt::{
+        format::Writer,
+        time::{FormatTime, SystemTime},
+    },
+    layer::Context,
+    registry::LookupSpan,
+    Layer,
+};
+
+use crate::{
+    error::{self, Error},
+    extensions::valid_id,
+};
+
+/// Name of the span entered for every run, with the extension `id` as field
+pub const EXTENSION_SPAN: &str = "extension";
+/// Target of the events forwarded from extensions
+pub const EXTENSION_TARGET: &str = "extension";
+/// Size at which a log file is rotated, the previous one is kept as `<id>.log.1`
+const MAX_LOG_SIZE: u64 = 256 * 1024;
+/// Number of lines returned by [`get_extension_logs`]
+const RECENT_LINES: usize = 200;
+
+static LOGS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Sets the directory of the log files, nothing is written before
+pub fn init(dir: PathBuf) -> io::Result<()> {
+    fs::create_dir_all(&dir)?;
+    let _ = LOGS_DIR.set(dir);
+    Ok(())
+}
+
+/// The layer writing the log files, added to the subscriber in `main.rs` with [`filter`]
+pub fn layer() -> ExtensionLogs {
+    ExtensionLogs::default()
+}
+
+/// What the log files get: all an extension logs, even debug output,
+/// and events of the app from info on
+pub fn filter() -> Targets {
+    Targets::new()
+        .with_default(LevelFilter::INFO)
+        .with_target(EXTENSION_TARGET, LevelFilter::TRACE)
+}
+
+#[derive(Default)]
+pub struct ExtensionLogs {
+    /// Runs of different extensions log in parallel, rotating has to happen at once
+    lock: Mutex<()>,
+}
+
+/// Id of the extension, stored with its span
+struct ExtensionId(String);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for ExtensionLogs {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != EXTENSION_SPAN {
+            return;
+        }
+        let mut visitor = FieldValue::new("id");
+        attrs.record(&mut visitor);
+        if let (Some(value), Some(span)) = (visitor.value, ctx.span(id)) {
+            span.extensions_mut().insert(ExtensionId(value));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(dir) = LOGS_DIR.get() else {
+            return;
+        };
+        let Some(id) = ctx.event_scope(event).and_then(|scope| {
+            scope
+                .from_root()
+                .find_map(|span| span.extensions().get::<ExtensionId>().map(|e| e.0.clone()))
+        }) else {
+            return;
+        };
+
+        let mut line = String::new();
+        let _ = SystemTime.format_time(&mut Writer::new(&mut line));
+        let _ = write!(line, " {:>5} ", event.metadata().level());
+        event.record(&mut LineWriter(&mut line));
+        line.push('\n');
+
+        let _guard = self.lock.lock();
+        // Logging the failure would end up here again
+        if let Err(e) = append(&log_path(dir, &id), &line) {
+            eprintln!("failed to write log of extension '{id}': {e}");
+        }
+    }
+}
+
+/// Reads the value of a single field
+struct FieldValue {
+    name: &'static str,
+    value: Option<String>,
+}
+
+impl FieldValue {
+    fn new(name: &'static str) -> Self {
+        Self { name, value: None }
+    }
+}
+
+impl Visit for FieldValue {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.name {
+            self.value = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.name {
+            self.value = Some(format!("{value:?}"));
+        }
+    }
+}
+
+/// Writes the message of an event followed by its other fields
+struct LineWriter<'a>(&'a mut String);
+
+impl Visit for LineWriter<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.0, "{value:?}"),
+            name => write!(self.0, " {name}={value:?}"),
+        };
+    }
+}
+
+fn log_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.log"))
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("log.1")
+}
+
+/// Appends `line` to the log at `path`, rotating it once it gets too big
+fn append(path: &Path, line: &str) -> io::Result<()> {
+    let size = fs::metadata(path).map_or(0, |m| m.len());
+    if size > 0 && size + line.len() as u64 > MAX_LOG_SIZE {
+        fs::rename(path, rotated_path(path))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+/// Returns the last `count` lines of the log at `path`, including the rotated one
+fn recent_lines(path: &Path, count: usize) -> String {
+    let mut logs = fs::read_to_string(rotated_path(path)).unwrap_or_default();
+    logs += &fs::read_to_string(path).unwrap_or_default();
+
+    let lines: Vec<&str> = logs.lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+/// Removes the logs of extension `id` once it is deleted.
+/// Updates keep them, so earlier runs can be compared with those of the new version.
+pub fn remove(id: &str) -> io::Result<()> {
+    let Some(dir) = LOGS_DIR.get() else {
+        return Ok(());
+    };
+    let path = log_path(dir, id);
+    for path in [rotated_path(&path), path] {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the recent log output of extension `id`, empty if it didn't log anything yet
+#[tauri::command]
+pub fn get_extension_logs(id: String) -> error::Result<String> {
+    // Ids name the files, so they may not point elsewhere
+    valid_id(&id)?;
+    let dir = LOGS_DIR
+        .get()
+        .ok_or_else(|| Error::FileSystem("logs are not set up".into()))?;
+    Ok(recent_lines(&log_path(dir, &id), RECENT_LINES))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_rotate_and_keep_recent_lines() {
+        let dir = std::env::temp_dir().join(format!("pointy-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = log_path(&dir, "math");
+
+        let line = format!("{}\n", "x".repeat(1023));
+        for _ in 0..(MAX_LOG_SIZE / 1024 + 1) {
+            append(&path, &line).unwrap();
+        }
+        append(&path, "last\n").unwrap();
+
+        assert!(rotated_path(&path).exists());
+        assert!(fs::metadata(&path).unwrap().len() <= MAX_LOG_SIZE);
+
+        let recent = recent_lines(&path, 3);
+        assert_eq!(recent.lines().count(), 3);
+        assert!(recent.ends_with("last"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn debug_output_of_extensions_is_kept() {
+        let filter = filter();
+        assert!(filter.would_enable(EXTENSION_TARGET, &tracing::Level::DEBUG));
+        assert!(filter.would_enable("pointy_lib::runs", &tracing::Level::INFO));
+        assert!(!filter.would_enable("pointy_lib::runs", &tracing::Level::DEBUG));
+    }
+}
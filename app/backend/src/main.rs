@@ -1,16 +1,38 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-fn main() {
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // Started by a running instance to run an extension, see `runs.rs`
+    #[cfg(unix)]
+    if std::env::args().nth(1).as_deref() == Some(pointy_runner::RUNNER_ARG) {
+        return pointy_runner::child::run(std::env::args().skip(1));
+    }
+
     logging();
 
-    pointy_lib::run()
+    // Subcommands like `pointy toggle` are forwarded to the running instance
+    #[cfg(unix)]
+    if std::env::args()
+        .nth(1)
+        .is_some_and(|arg| pointy_cli::is_command(&arg))
+    {
+        return pointy_cli::run(std::env::args().skip(1));
+    }
+
+    pointy_lib::run();
+    ExitCode::SUCCESS
 }
 
 fn logging() {
-    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+    use tracing_subscriber::{
+        fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+    };
 
+    // `RUST_LOG` only applies to the output, the log files of extensions have their own filter
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into());
     tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
-        .with(tracing_subscriber::fmt::layer())
+        .with(fmt::layer().with_filter(filter))
+        .with(pointy_lib::logs::layer().with_filter(pointy_lib::logs::filter()))
         .init();
 }
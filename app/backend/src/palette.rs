@@ -0,0 +1,339 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, State};
+use tracing::warn;
+
+use crate::{
+    config::write_atomic,
+    error::{self, Error},
+    extensions::{get_installed_extensions, ExtensionManifest, InstalledExtensionInfo},
+    geometry::wheel_position,
+    wheel::cursor_monitor,
+    AppState,
+};
+
+/// Bonus for matching the start of a word, e.g. `q` in `generate_qrcode`
+const WORD_START_BONUS: i64 = 8;
+/// Bonus for matching right after the previous match
+const CONSECUTIVE_BONUS: i64 = 5;
+/// Upper bound of the bonus for recent use, so a good match still beats a frequent one
+const MAX_USAGE_BONUS: f64 = 20.;
+
+/// How often and when an extension was run
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Usage {
+    pub count: u32,
+    /// Seconds since the unix epoch
+    pub last_used: u64,
+}
+
+impl Usage {
+    /// Ranking bonus which decays with the days since the last use
+    pub fn bonus(&self, now: u64) -> f64 {
+        let days = now.saturating_sub(self.last_used) as f64 / 86400.;
+        (self.count as f64 * 4. / (1. + days)).min(MAX_USAGE_BONUS)
+    }
+}
+
+/// Persists the usage of extensions for ranking them in the palette
+pub struct UsageStore {
+    path: PathBuf,
+    usage: Mutex<HashMap<String, Usage>>,
+}
+
+impl UsageStore {
+    /// Opens the usage at `path`, starting over if it is missing or corrupted
+    pub fn open(path: PathBuf) -> Self {
+        let usage = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| match serde_json::from_str(&data) {
+                Ok(usage) => Some(usage),
+                Err(e) => {
+                    warn!(error = %e, path = %path.display(), "usage is corrupted, starting over");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            usage: Mutex::new(usage),
+        }
+    }
+
+    /// Returns a copy of the current usage
+    pub fn get(&self) -> error::Result<HashMap<String, Usage>> {
+        Ok(self.usage.lock()?.clone())
+    }
+
+    /// Records a run of extension `id`
+    pub fn record(&self, id: &str) -> error::Result<()> {
+        let mut usage = self.usage.lock()?;
+        let entry = usage.entry(id.to_string()).or_default();
+        entry.count += 1;
+        entry.last_used = now();
+
+        write_atomic(&self.path, serde_json::to_string(&*usage)?.as_bytes())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Scores how well `query` matches `text`, ignoring case and whitespace in the query.
+/// All query characters have to appear in order, `None` means no match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let pos = next + text[next..].iter().position(|t| *t == c)?;
+
+        score += 1;
+        if pos == 0 || !text[pos - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        if previous.is_some_and(|p| p + 1 == pos) {
+            score += CONSECUTIVE_BONUS;
+        }
+
+        previous = Some(pos);
+        next = pos + 1;
+    }
+
+    Some(score)
+}
+
+/// Scores `query` against the fields of an extension, preferring matches in its name
+pub fn match_score(query: &str, manifest: &ExtensionManifest) -> Option<i64> {
+    let fields = [
+        (manifest.name.as_str(), 3),
+        (manifest.id.as_str(), 2),
+        (manifest.description.as_str(), 1),
+    ];
+    let keywords = manifest.keywords.iter().map(|k| (k.as_str(), 2));
+
+    fields
+        .into_iter()
+        .chain(keywords)
+        .filter_map(|(text, weight)| Some(fuzzy_score(query, text)? * weight))
+        .max()
+}
+
+/// Filters `extensions` by `query` and orders them by match and recent use
+pub fn rank<'a>(
+    query: &str,
+    extensions: &'a [ExtensionManifest],
+    usage: &HashMap<String, Usage>,
+    now: u64,
+) -> Vec<&'a ExtensionManifest> {
+    let mut ranked: Vec<(f64, &ExtensionManifest)> = extensions
+        .iter()
+        .filter_map(|manifest| {
+            let score = match_score(query, manifest)?;
+            let bonus = usage.get(&manifest.id).map_or(0., |u| u.bonus(now));
+            Some((score as f64 + bonus, manifest))
+        })
+        .collect();
+
+    ranked.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .total_cmp(a_score)
+            .then_with(|| {
+                let last_used = |m: &ExtensionManifest| usage.get(&m.id).map(|u| u.last_used);
+                Reverse(last_used(a)).cmp(&Reverse(last_used(b)))
+            })
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    ranked.into_iter().map(|(_, manifest)| manifest).collect()
+}
+
+/// Returns the enabled extensions matching `query`, best match first
+#[tauri::command]
+pub fn search_palette(
+    query: String,
+    app_state: State<'_, AppState>,
+) -> error::Result<Vec<InstalledExtensionInfo>> {
+    let enabled: Vec<InstalledExtensionInfo> = get_installed_extensions(app_state.clone())?
+        .into_iter()
+        .filter(|e| e.enabled)
+        .collect();
+    let manifests: Vec<ExtensionManifest> = enabled.iter().map(|e| e.manifest.clone()).collect();
+
+    let usage = app_state.usage.get()?;
+    Ok(rank(&query, &manifests, &usage, now())
+        .into_iter()
+        .filter_map(|m| enabled.iter().find(|e| e.manifest.id == m.id).cloned())
+        .collect())
+}
+
+/// Shows the palette centered on the monitor under the cursor
+pub fn show(app: &AppHandle) -> error::Result<()> {
+    let window = app
+        .get_webview_window("palette")
+        .ok_or_else(|| Error::Tauri("palette window is missing".into()))?;
+
+    let (_, monitor) = cursor_monitor(app)?;
+    let size = window.outer_size()?.to_logical(window.scale_factor()?);
+    let (x, y) = wheel_position(
+        monitor.work_area.centre(),
+        (size.width, size.height),
+        &monitor,
+    );
+    window.set_position(PhysicalPosition::new(x.round() as i32, y.round() as i32))?;
+
+    window.show()?;
+    window.set_focus()?;
+    window.emit("open-palette", ())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(id: &str, name: &str, description: &str, keywords: &[&str]) -> ExtensionManifest {
+        ExtensionManifest {
+            id: id.into(),
+            name: name.into(),
+            author: "pointy".into(),
+            version: "0.1.0".parse().unwrap(),
+            description: description.into(),
+            latest_url: String::new(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            permissions: Vec::new(),
+            kind: Default::default(),
+        }
+    }
+
+    fn ids(ranked: Vec<&ExtensionManifest>) -> Vec<&str> {
+        ranked.into_iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn fuzzy_requires_all_characters_in_order() {
+        assert!(fuzzy_score("qr", "generate_qrcode").is_some());
+        assert!(fuzzy_score("cq", "generate_qrcode").is_none());
+        assert!(fuzzy_score("x", "generate_qrcode").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn fuzzy_ignores_case_and_whitespace() {
+        assert_eq!(
+            fuzzy_score("QR code", "qrcode"),
+            fuzzy_score("qrcode", "qrcode")
+        );
+    }
+
+    #[test]
+    fn fuzzy_prefers_consecutive_and_word_starts() {
+        let consecutive = fuzzy_score("code", "barcode").unwrap();
+        let scattered = fuzzy_score("code", "bacon_dye").unwrap();
+        assert!(consecutive > scattered);
+
+        let word_start = fuzzy_score("p", "secure password").unwrap();
+        let inner = fuzzy_score("p", "screenshot capture").unwrap();
+        assert!(word_start > inner);
+    }
+
+    #[test]
+    fn name_matches_beat_description_matches() {
+        let extensions = [
+            manifest("a", "Text Metadata", "Counts words of a qr", &[]),
+            manifest("b", "QR Code", "Generates an image", &[]),
+        ];
+        assert_eq!(ids(rank("qr", &extensions, &HashMap::new(), 0)), ["b", "a"]);
+    }
+
+    #[test]
+    fn keywords_are_matched() {
+        let extensions = [
+            manifest("math", "Math", "Solves equations", &["calculator"]),
+            manifest("qr", "QR Code", "Generates an image", &[]),
+        ];
+        assert_eq!(ids(rank("calc", &extensions, &HashMap::new(), 0)), ["math"]);
+    }
+
+    #[test]
+    fn recent_use_ranks_higher() {
+        let extensions = [
+            manifest("password", "Password", "", &[]),
+            manifest("screenshot", "Screenshot", "", &[]),
+        ];
+        let now = 10 * 86400;
+        let usage = HashMap::from([(
+            "screenshot".to_string(),
+            Usage {
+                count: 3,
+                last_used: now - 60,
+            },
+        )]);
+
+        assert_eq!(
+            ids(rank("", &extensions, &usage, now)),
+            ["screenshot", "password"]
+        );
+        assert_eq!(
+            ids(rank("s", &extensions, &usage, now)),
+            ["screenshot", "password"]
+        );
+    }
+
+    #[test]
+    fn usage_bonus_decays_and_is_bounded() {
+        let now = 100 * 86400;
+        let fresh = Usage {
+            count: 2,
+            last_used: now,
+        };
+        let old = Usage {
+            count: 2,
+            last_used: now - 30 * 86400,
+        };
+        assert!(fresh.bonus(now) > old.bonus(now));
+
+        let frequent = Usage {
+            count: 1000,
+            last_used: now,
+        };
+        assert_eq!(frequent.bonus(now), MAX_USAGE_BONUS);
+    }
+
+    #[test]
+    fn ties_are_ordered_by_name() {
+        let extensions = [
+            manifest("b", "Beta", "", &[]),
+            manifest("a", "Alpha", "", &[]),
+        ];
+        assert_eq!(ids(rank("", &extensions, &HashMap::new(), 0)), ["a", "b"]);
+    }
+
+    #[test]
+    fn usage_is_kept_across_restarts() {
+        let path = std::env::temp_dir().join(format!("pointy-usage-{}.json", std::process::id()));
+        let store = UsageStore::open(path.clone());
+        store.record("math").unwrap();
+        store.record("math").unwrap();
+
+        let usage = UsageStore::open(path.clone()).get().unwrap();
+        assert_eq!(usage["math"].count, 2);
+        assert!(usage["math"].last_used > 0);
+
+        fs::remove_file(path).unwrap();
+    }
+}
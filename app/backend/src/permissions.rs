@@ -0,0 +1,171 @@
+//! Approval of the permissions extensions declare in their manifest.
+//!
+//! A run only gets the permissions which are both declared and approved,
+//! so an update asking for more has to be approved again before it gets them.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use pointy_api::Permission;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::{
+    config::write_atomic,
+    error::{self, Error},
+    extensions::read_manifest,
+    AppState,
+};
+
+/// Persists the permissions approved per extension
+pub struct PermissionStore {
+    path: PathBuf,
+    granted: Mutex<BTreeMap<String, Vec<Permission>>>,
+}
+
+impl PermissionStore {
+    /// Opens the approvals at `path`, if corrupted they have to be given again
+    pub fn open(path: PathBuf) -> Self {
+        let granted = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| match serde_json::from_str(&data) {
+                Ok(granted) => Some(granted),
+                Err(e) => {
+                    warn!(error = %e, path = %path.display(), "permissions are corrupted, starting over");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path,
+            granted: Mutex::new(granted),
+        }
+    }
+
+    /// The permissions approved for extension `id`
+    pub fn granted(&self, id: &str) -> error::Result<Vec<Permission>> {
+        Ok(self.granted.lock()?.get(id).cloned().unwrap_or_default())
+    }
+
+    /// Replaces the approved permissions of extension `id`, none removes it
+    pub fn set(&self, id: &str, permissions: Vec<Permission>) -> error::Result<()> {
+        let mut granted = self.granted.lock()?;
+        let mut updated = granted.clone();
+        if permissions.is_empty() {
+            updated.remove(id);
+        } else {
+            updated.insert(id.to_string(), permissions);
+        }
+        // Only kept once stored, so a failed write doesn't grant anything for this session
+        write_atomic(&self.path, serde_json::to_string(&updated)?.as_bytes())?;
+        *granted = updated;
+        Ok(())
+    }
+}
+
+/// The declared permissions which weren't approved yet
+pub fn pending(declared: &[Permission], granted: &[Permission]) -> Vec<Permission> {
+    declared
+        .iter()
+        .filter(|p| !granted.contains(p))
+        .cloned()
+        .collect()
+}
+
+/// The permissions of a run, declared and approved, with `~` resolved to `home`
+pub fn effective(declared: &[Permission], granted: &[Permission], home: &Path) -> Vec<Permission> {
+    declared
+        .iter()
+        .filter(|p| granted.contains(p))
+        .map(|p| match p {
+            Permission::Filesystem(path) => match path.strip_prefix("~") {
+                Ok(rest) => Permission::Filesystem(home.join(rest)),
+                Err(_) => p.clone(),
+            },
+            _ => p.clone(),
+        })
+        .collect()
+}
+
+/// The permissions a run of extension `id` gets
+pub fn for_run(app: &AppHandle, id: &str) -> error::Result<Vec<Permission>> {
+    let app_state = app.state::<AppState>();
+    let manifest = read_manifest(&app_state.extensions_path, id)?;
+    let granted = app_state.permissions.granted(id)?;
+    let home = app.path().home_dir()?;
+    Ok(effective(&manifest.permissions, &granted, &home))
+}
+
+/// Approves `permissions`, the ones the user reviewed, if extension `id` still declares them
+#[tauri::command]
+pub fn grant_permissions(
+    id: String,
+    permissions: Vec<Permission>,
+    app: AppHandle,
+) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+    let manifest = read_manifest(&app_state.extensions_path, &id)
+        .map_err(|_| Error::FileSystem(format!("extension '{id}' is not installed")))?;
+    // It may have been updated since
+    if manifest.permissions != permissions {
+        return Err(Error::PermissionsChanged(id));
+    }
+    app_state.permissions.set(&id, permissions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_approved_permissions_are_effective() {
+        let declared = [
+            Permission::ClipboardRead,
+            Permission::Network,
+            Permission::Filesystem("~/Pictures".into()),
+        ];
+        let granted = [
+            Permission::ClipboardRead,
+            Permission::ClipboardWrite,
+            Permission::Filesystem("~/Pictures".into()),
+        ];
+
+        assert_eq!(pending(&declared, &granted), [Permission::Network]);
+        // Approved but no longer declared ones are dropped as well
+        assert_eq!(
+            effective(&declared, &granted, Path::new("/home/me")),
+            [
+                Permission::ClipboardRead,
+                Permission::Filesystem("/home/me/Pictures".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn approvals_are_kept_once_stored() {
+        let dir = std::env::temp_dir().join(format!("pointy-permissions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("permissions.json");
+
+        let store = PermissionStore::open(path.clone());
+        store.set("a", vec![Permission::Network]).unwrap();
+        assert_eq!(
+            PermissionStore::open(path.clone()).granted("a").unwrap(),
+            [Permission::Network]
+        );
+        store.set("a", Vec::new()).unwrap();
+        assert!(PermissionStore::open(path).granted("a").unwrap().is_empty());
+
+        // Nothing is granted if it can't be stored
+        let store = PermissionStore::open(dir.join("missing/permissions.json"));
+        assert!(store.set("a", vec![Permission::Network]).is_err());
+        assert!(store.granted("a").unwrap().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use tauri::State;
+
+use crate::{
+    config::Config,
+    error::{self, Error},
+    AppState,
+};
+
+/// Creates a new profile as a copy of the active one
+#[tauri::command]
+pub fn create_profile(
+    name: String,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    let name = valid_name(name)?;
+
+    app_state.config.update_at(revision, |config| {
+        if config.profiles.contains_key(&name) {
+            return Err(Error::Profile(format!("profile '{name}' already exists")));
+        }
+
+        let profile = config.profile().clone();
+        config.profiles.insert(name, profile);
+        Ok(())
+    })
+}
+
+/// Renames the profile `name` to `new_name`
+#[tauri::command]
+pub fn rename_profile(
+    name: String,
+    new_name: String,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    let new_name = valid_name(new_name)?;
+
+    app_state.config.update_at(revision, |config| {
+        if config.profiles.contains_key(&new_name) {
+            return Err(Error::Profile(format!(
+                "profile '{new_name}' already exists"
+            )));
+        }
+        let profile = config
+            .profiles
+            .remove(&name)
+            .ok_or_else(|| unknown_profile(&name))?;
+
+        if config.active_profile == name {
+            config.active_profile = new_name.clone();
+        }
+        config.profiles.insert(new_name, profile);
+        Ok(())
+    })
+}
+
+/// Deletes the profile `name`. If it is active, the first remaining profile becomes active.
+#[tauri::command]
+pub fn delete_profile(
+    name: String,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state.config.update_at(revision, |config| {
+        if !config.profiles.contains_key(&name) {
+            return Err(unknown_profile(&name));
+        }
+        if config.profiles.len() == 1 {
+            return Err(Error::Profile("the last profile can't be deleted".into()));
+        }
+
+        if config.active_profile == name {
+            let next = config.profiles.keys().find(|p| **p != name).cloned();
+            activate_profile(config, &next.unwrap_or_default())?;
+        }
+        config.profiles.remove(&name);
+        Ok(())
+    })
+}
+
+/// Makes `name` the active profile
+#[tauri::command]
+pub fn switch_profile(
+    name: String,
+    revision: u64,
+    app_state: State<'_, AppState>,
+) -> error::Result<Config> {
+    app_state
+        .config
+        .update_at(revision, |config| activate_profile(config, &name))
+}
+
+/// Switches `config` to the profile `name`
+pub fn activate_profile(config: &mut Config, name: &str) -> error::Result<()> {
+    if !config.profiles.contains_key(name) {
+        return Err(unknown_profile(name));
+    }
+
+    config.active_profile = name.to_string();
+    Ok(())
+}
+
+fn valid_name(name: String) -> error::Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Profile("profile name must not be empty".into()));
+    }
+    Ok(name.to_string())
+}
+
+fn unknown_profile(name: &str) -> Error {
+    Error::Profile(format!("profile '{name}' does not exist"))
+}
@@ -0,0 +1,563 @@
+//! Runs of extensions on a worker pool, so a slow or stuck extension doesn't stall the app.
+//!
+//! Every run gets an id and reports its progress as `run-update` events.
+//! Its logs are kept per extension, see [`crate::logs`].
+//! On unix extensions run in a runner process, see [`pointy_runner`], which captures their
+//! output for the developer console and is killed if it doesn't stop once asked to.
+//! Elsewhere they run in-process, where cancelling and timeouts are cooperative via
+//! [`pointy_api::is_cancelled`], as a native library can't be interrupted safely.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use pointy_api::{Level, Permission};
+use pointy_runner::{ExtensionKind, Limits, OutputLine, RunnerMessage};
+use serde::{Deserialize, Serialize};
+use tauri::{async_runtime, AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use tracing::{debug, error, info, info_span, warn};
+
+use crate::{
+    config::Config,
+    error::{self, Error},
+    extensions::{read_manifest, valid_id},
+    logs::{EXTENSION_SPAN, EXTENSION_TARGET},
+    permissions, AppState,
+};
+
+pub type RunId = u64;
+
+/// Runs kept for the developer console, older ones are dropped
+const MAX_RUN_RECORDS: usize = 50;
+/// Whether extensions run in a runner process, only then is their output captured
+/// and a stuck run killed
+pub const ISOLATED: bool = cfg!(unix);
+/// Crashes, timeouts or limit breaches in a row after which an extension is quarantined
+const QUARANTINE_AFTER: u32 = 3;
+
+/// State of a run, sent with every `run-update` event
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", content = "value")]
+pub enum RunState {
+    Started,
+    /// Reported by the extension, `fraction` going from 0 to 1
+    Progress {
+        fraction: f32,
+        message: String,
+    },
+    Finished,
+    Failed(Error),
+}
+
+/// Crashes, timeouts and limit breaches in a row by extension id
+#[derive(Default)]
+struct Strikes(HashMap<String, u32>);
+
+impl Strikes {
+    /// Counts the outcome of a run of extension `id`.
+    /// Returns why it should be quarantined once it failed [`QUARANTINE_AFTER`] times in a row.
+    fn count(&mut self, id: &str, outcome: &error::Result<()>) -> Option<String> {
+        let error = match outcome {
+            Err(e @ (Error::Crashed(_) | Error::TimedOut(_) | Error::LimitExceeded(_))) => e,
+            // Being cancelled says nothing about the extension
+            Err(Error::Cancelled) => return None,
+            // Returning, even with an error, resets the count
+            _ => {
+                self.0.remove(id);
+                return None;
+            }
+        };
+        let count = self.0.entry(id.to_string()).or_default();
+        *count += 1;
+        if *count < QUARANTINE_AFTER {
+            return None;
+        }
+        self.0.remove(id);
+        Some(format!(
+            "it failed {QUARANTINE_AFTER} times in a row, last with: {error}"
+        ))
+    }
+}
+
+/// Payload of the `run-update` event
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunUpdate {
+    pub run_id: RunId,
+    /// Id of the extension
+    pub id: String,
+    pub state: RunState,
+}
+
+/// Payload of the `extension-notification` event
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExtensionNotification {
+    /// Id of the extension
+    pub id: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// A finished run, shown in the developer console
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunRecord {
+    pub run_id: RunId,
+    /// Id of the extension
+    pub id: String,
+    /// Milliseconds since the unix epoch
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// What the extension wrote to stdout and stderr
+    pub output: Vec<OutputLine>,
+    pub error: Option<Error>,
+    /// Whether it ran in a runner process, see [`ISOLATED`]
+    pub isolated: bool,
+}
+
+struct ActiveRun {
+    cancelled: Arc<AtomicBool>,
+}
+
+/// The runs currently in progress and the recently finished ones
+#[derive(Default)]
+pub struct Runs {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<RunId, ActiveRun>>,
+    records: Mutex<VecDeque<RunRecord>>,
+    strikes: Mutex<Strikes>,
+}
+
+/// Fails if `id` can't be run, before it is used in any path
+fn check_runnable(config: &Config, id: &str) -> error::Result<()> {
+    // Ids come from the frontend, the socket and hotkeys alike
+    valid_id(id)?;
+    // Groups only open a sub-wheel
+    if config.profile().groups.contains_key(id) {
+        return Err(Error::Wheel(format!("'{id}' is a group and can't be run")));
+    }
+    if let Some(reason) = config.quarantined.get(id) {
+        return Err(Error::Run(format!(
+            "'{id}' was disabled as {reason}, enable it again to run it"
+        )));
+    }
+    Ok(())
+}
+
+/// Starts a run of extension `id` and returns right away.
+/// The receiver gets the outcome once the run finished, failed or timed out.
+pub fn start(
+    app: &AppHandle,
+    id: String,
+) -> error::Result<(RunId, mpsc::Receiver<error::Result<()>>)> {
+    let app_state = app.state::<AppState>();
+    let (timeout, limits, record) = {
+        let config = app_state.config.read()?;
+        check_runnable(&config, &id)?;
+
+        let overrides = config.limits.get(&id).cloned().unwrap_or_default();
+        let limits = Limits {
+            cpu_time: overrides.cpu_time.map(Duration::from_secs),
+            memory: Some(overrides.memory.unwrap_or(config.memory_limit) << 20),
+        };
+        let timeout = overrides.wall_time.unwrap_or(config.run_timeout).max(1);
+        (timeout, limits, config.developer_console)
+    };
+
+    // A missing manifest is reported as not installed below
+    let kind = read_manifest(&app_state.extensions_path, &id)
+        .map(|manifest| manifest.kind)
+        .unwrap_or_default();
+    let path = app_state.extensions_path.join(&id).join(kind.filename());
+    if !path.exists() {
+        return Err(Error::LibLoading(format!(
+            "extension '{id}' is not installed"
+        )));
+    }
+
+    // Kept across runs and updates, only removed along with the extension if asked to
+    let data_dir = app_state.extension_data_path.join(&id);
+    fs::create_dir_all(&data_dir)?;
+
+    let permissions = permissions::for_run(app, &id)?;
+
+    let run_id = app_state.runs.next_id.fetch_add(1, Ordering::Relaxed);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    app_state.runs.active.lock()?.insert(
+        run_id,
+        ActiveRun {
+            cancelled: cancelled.clone(),
+        },
+    );
+    emit(app, run_id, &id, RunState::Started);
+
+    // Everything logged during the run ends up in the log of the extension
+    let span = info_span!(EXTENSION_SPAN, id = %id, run_id);
+
+    let (tx, rx) = mpsc::channel();
+    let app = app.clone();
+    async_runtime::spawn_blocking(move || {
+        let _entered = span.enter();
+        let started_at = now_ms();
+        let start = Instant::now();
+
+        let extension = (kind, path.as_path(), data_dir.as_path());
+        let (outcome, output) = execute(
+            &app,
+            run_id,
+            &id,
+            extension,
+            &permissions,
+            (timeout, limits),
+            cancelled,
+        );
+
+        if record {
+            let record = RunRecord {
+                run_id,
+                id: id.clone(),
+                started_at,
+                duration_ms: start.elapsed().as_millis() as u64,
+                output,
+                error: outcome.as_ref().err().cloned(),
+                isolated: ISOLATED,
+            };
+            if let Ok(mut records) = app.state::<AppState>().runs.records.lock() {
+                records.push_front(record);
+                records.truncate(MAX_RUN_RECORDS);
+            }
+        }
+        finish(&app, run_id, &id, &outcome);
+        let _ = tx.send(outcome);
+    });
+
+    Ok((run_id, rx))
+}
+
+/// Runs the extension in a runner process, returning the outcome and the captured output
+#[cfg(unix)]
+fn execute(
+    app: &AppHandle,
+    run_id: RunId,
+    id: &str,
+    (kind, path, data_dir): (ExtensionKind, &Path, &Path),
+    permissions: &[Permission],
+    (timeout, limits): (u64, Limits),
+    cancelled: Arc<AtomicBool>,
+) -> (error::Result<()>, Vec<OutputLine>) {
+    use pointy_runner::{Limit, RunError, RunOptions};
+
+    // The app itself acts as runner
+    let program = match std::env::current_exe() {
+        Ok(program) => program,
+        Err(e) => return (Err(e.into()), Vec::new()),
+    };
+    let options = RunOptions {
+        program: &program,
+        kind,
+        path,
+        data_dir,
+        permissions,
+        timeout: Duration::from_secs(timeout),
+        limits,
+        cancelled: &cancelled,
+    };
+
+    match pointy_runner::run(&options, |message| handle_message(app, run_id, id, message)) {
+        Ok(outcome) => {
+            let result = outcome.result.map_err(|e| match e {
+                RunError::Failed(e) => Error::LibLoading(e),
+                RunError::Cancelled => Error::Cancelled,
+                RunError::TimedOut => Error::TimedOut(timeout),
+                RunError::Crashed(status) => Error::Crashed(status),
+                RunError::LimitExceeded(Limit::CpuTime) => Error::LimitExceeded(format!(
+                    "{} seconds of CPU time",
+                    limits.cpu_time.unwrap_or_default().as_secs()
+                )),
+                RunError::LimitExceeded(Limit::Memory) => Error::LimitExceeded(format!(
+                    "{} MiB of memory",
+                    limits.memory.unwrap_or_default() >> 20
+                )),
+            });
+            (result, outcome.output)
+        }
+        Err(e) => (
+            Err(Error::Run(format!("failed to start runner: {e}"))),
+            Vec::new(),
+        ),
+    }
+}
+
+/// Runs the extension in-process, its output isn't captured.
+/// A stuck run can't be stopped either, it is only asked to and keeps running after its timeout.
+#[cfg(not(unix))]
+fn execute(
+    app: &AppHandle,
+    run_id: RunId,
+    id: &str,
+    (kind, path, data_dir): (ExtensionKind, &Path, &Path),
+    permissions: &[Permission],
+    // Only the memory of WebAssembly extensions can be limited within the app
+    (timeout, limits): (u64, Limits),
+    cancelled: Arc<AtomicBool>,
+) -> (error::Result<()>, Vec<OutputLine>) {
+    use pointy_runner::Extension;
+
+    // The worker owns the extension, so it stays loaded even if the run outlives its timeout
+    let (worker_tx, worker_rx) = mpsc::channel();
+    let span = tracing::Span::current();
+    let (worker_app, worker_id) = (app.clone(), id.to_string());
+    let (path, data_dir) = (path.to_path_buf(), data_dir.to_path_buf());
+    let (permissions, worker_cancelled) = (permissions.to_vec(), cancelled.clone());
+    std::thread::spawn(move || {
+        let _entered = span.enter();
+        let sink =
+            |message: RunnerMessage| handle_message(&worker_app, run_id, &worker_id, message);
+        let result = Extension::load(kind, &path).and_then(|extension| {
+            extension.with_memory_limit(limits.memory).call(
+                &data_dir,
+                &permissions,
+                &worker_cancelled,
+                &sink,
+            )
+        });
+        let _ = worker_tx.send(result);
+    });
+
+    let outcome = match worker_rx.recv_timeout(Duration::from_secs(timeout)) {
+        Ok(_) if cancelled.load(Ordering::Relaxed) => Err(Error::Cancelled),
+        Ok(result) => result.map_err(Error::LibLoading),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Err(Error::TimedOut(timeout))
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err(Error::Run("extension run was aborted".into()))
+        }
+    };
+    (outcome, Vec::new())
+}
+
+/// Handles a call of the extension into the host, within the span of its run
+fn handle_message(app: &AppHandle, run_id: RunId, id: &str, message: RunnerMessage) {
+    match message {
+        RunnerMessage::Progress { fraction, message } => {
+            emit(app, run_id, id, RunState::Progress { fraction, message })
+        }
+        RunnerMessage::Notify { title, body } => show_notification(app, id, title, body),
+        RunnerMessage::Log { level, message } => match level {
+            l if l == Level::Error as u32 => error!(target: EXTENSION_TARGET, "{message}"),
+            l if l == Level::Warn as u32 => warn!(target: EXTENSION_TARGET, "{message}"),
+            l if l == Level::Debug as u32 => debug!(target: EXTENSION_TARGET, "{message}"),
+            _ => info!(target: EXTENSION_TARGET, "{message}"),
+        },
+        // Reported by the outcome of the run
+        RunnerMessage::LimitExceeded { .. } | RunnerMessage::Finished { .. } => {}
+    }
+}
+
+fn finish(app: &AppHandle, run_id: RunId, id: &str, outcome: &error::Result<()>) {
+    let app_state = app.state::<AppState>();
+    if let Ok(mut active) = app_state.runs.active.lock() {
+        active.remove(&run_id);
+    }
+
+    match outcome {
+        Ok(()) => {
+            if let Err(e) = app_state.usage.record(id) {
+                warn!(error = %e, "failed to record extension usage");
+            }
+            emit(app, run_id, id, RunState::Finished);
+        }
+        Err(e) => {
+            warn!(error = %e, id, run_id, "extension run failed");
+            emit(app, run_id, id, RunState::Failed(e.clone()));
+        }
+    }
+
+    strike(app, id, outcome);
+}
+
+/// Counts the outcome of a run of extension `id`, quarantining it once it failed too often
+fn strike(app: &AppHandle, id: &str, outcome: &error::Result<()>) {
+    let app_state = app.state::<AppState>();
+    let Ok(mut strikes) = app_state.runs.strikes.lock() else {
+        return;
+    };
+    let Some(reason) = strikes.count(id, outcome) else {
+        return;
+    };
+    drop(strikes);
+
+    if let Err(e) = app_state.config.update(|config| {
+        config.quarantine(id, reason.clone());
+        Ok(())
+    }) {
+        warn!(error = %e, id, "failed to quarantine extension");
+        return;
+    }
+    warn!(id, reason, "quarantined extension");
+
+    let name = read_manifest(&app_state.extensions_path, id).map_or(id.to_string(), |m| m.name);
+    show_notification(
+        app,
+        id,
+        format!("{name} was disabled"),
+        format!("Because {reason}. Enable it again in the settings once fixed."),
+    );
+}
+
+fn emit(app: &AppHandle, run_id: RunId, id: &str, state: RunState) {
+    let update = RunUpdate {
+        run_id,
+        id: id.to_string(),
+        state,
+    };
+    if let Err(e) = app.emit("run-update", update) {
+        warn!(error = %e, "failed to emit run update");
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Shows a notification of extension `id` as a toast if the settings are open, otherwise by the system
+pub fn show_notification(app: &AppHandle, id: &str, title: String, body: String) {
+    let settings_open = app
+        .get_webview_window("settings")
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false);
+
+    let result = if settings_open {
+        let notification = ExtensionNotification {
+            id: id.to_string(),
+            title,
+            body,
+        };
+        app.emit_to("settings", "extension-notification", notification)
+            .map_err(|e| e.to_string())
+    } else {
+        app.notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+            .map_err(|e| e.to_string())
+    };
+    if let Err(e) = result {
+        warn!(error = %e, id, "failed to show notification");
+    }
+}
+
+/// Asks run `run_id` to stop, it finishes as cancelled once the extension returns or is killed
+#[tauri::command]
+pub fn cancel_extension_run(run_id: RunId, app_state: State<'_, AppState>) -> error::Result<()> {
+    let active = app_state.runs.active.lock()?;
+    let run = active
+        .get(&run_id)
+        .ok_or_else(|| Error::Run(format!("run {run_id} is not in progress")))?;
+    run.cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Returns the recent runs for the developer console, newest first.
+/// Runs are only recorded while the console is enabled.
+#[tauri::command]
+pub fn get_run_records(app_state: State<'_, AppState>) -> error::Result<Vec<RunRecord>> {
+    Ok(app_state.runs.records.lock()?.iter().cloned().collect())
+}
+
+/// Clears the runs of the developer console
+#[tauri::command]
+pub fn clear_run_records(app_state: State<'_, AppState>) -> error::Result<()> {
+    app_state.runs.records.lock()?.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Group;
+
+    use super::*;
+
+    #[test]
+    fn only_installable_ids_are_run() {
+        let mut config = Config::default();
+        config.profile_mut().groups.insert(
+            "tools".into(),
+            Group {
+                name: "Tools".into(),
+                entries: Vec::new(),
+            },
+        );
+        config.quarantine("crashy", "it crashed".into());
+
+        assert!(check_runnable(&config, "math").is_ok());
+        for id in ["../../some/dir", "..", "a/b", ""] {
+            assert!(
+                matches!(check_runnable(&config, id), Err(Error::InvalidId(_))),
+                "{id}"
+            );
+        }
+        assert!(matches!(
+            check_runnable(&config, "tools"),
+            Err(Error::Wheel(_))
+        ));
+        assert!(matches!(
+            check_runnable(&config, "crashy"),
+            Err(Error::Run(_))
+        ));
+    }
+
+    #[test]
+    fn extensions_failing_in_a_row_are_quarantined() {
+        let mut strikes = Strikes::default();
+        let crashed = Err(Error::Crashed("signal: 11 (SIGSEGV)".into()));
+
+        for _ in 1..QUARANTINE_AFTER {
+            assert_eq!(strikes.count("a", &crashed), None);
+        }
+        // Other extensions are counted on their own
+        assert_eq!(strikes.count("b", &Err(Error::TimedOut(5))), None);
+        let reason = strikes.count("a", &crashed).unwrap();
+        assert!(reason.contains("SIGSEGV"), "{reason}");
+        // Counted from zero again afterwards
+        assert_eq!(strikes.count("a", &crashed), None);
+    }
+
+    #[test]
+    fn returning_resets_the_strikes() {
+        let mut strikes = Strikes::default();
+        let exceeded = Err(Error::LimitExceeded("64 MiB of memory".into()));
+
+        for outcome in [Ok(()), Err(Error::LibLoading("failed".into()))] {
+            for _ in 1..QUARANTINE_AFTER {
+                assert_eq!(strikes.count("a", &exceeded), None);
+            }
+            assert_eq!(strikes.count("a", &outcome), None);
+        }
+        assert_eq!(strikes.count("a", &exceeded), None);
+    }
+
+    #[test]
+    fn cancelled_runs_are_not_counted() {
+        let mut strikes = Strikes::default();
+        let timed_out = Err(Error::TimedOut(5));
+
+        for _ in 1..QUARANTINE_AFTER {
+            assert_eq!(strikes.count("a", &timed_out), None);
+        }
+        // Neither a strike nor a reset
+        assert_eq!(strikes.count("a", &Err(Error::Cancelled)), None);
+        assert!(strikes.count("a", &timed_out).is_some());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use tauri::AppHandle;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+use crate::{
+    config::Config,
+    error::{self, Error},
+};
+
+/// What a global shortcut triggers
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Opens the main wheel or the named one
+    Wheel(Option<String>),
+    /// Opens the palette
+    Palette,
+    /// Runs an extension directly, bypassing the wheel
+    Extension(String),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Wheel(None) => write!(f, "the wheel"),
+            Action::Wheel(Some(name)) => write!(f, "wheel '{}'", name),
+            Action::Palette => write!(f, "the palette"),
+            Action::Extension(id) => write!(f, "extension '{}'", id),
+        }
+    }
+}
+
+/// Collects the shortcuts of the active profile, failing if one is bound twice
+pub fn bindings(config: &Config) -> error::Result<HashMap<Shortcut, Action>> {
+    let profile = config.profile();
+
+    let main_wheel = [(&profile.shortcut, Action::Wheel(None))];
+    let wheels = profile
+        .wheels
+        .iter()
+        .map(|(name, wheel)| (&wheel.shortcut, Action::Wheel(Some(name.clone()))));
+    let palette = profile
+        .palette_shortcut
+        .iter()
+        .map(|s| (s, Action::Palette));
+    let hotkeys = profile
+        .hotkeys
+        .iter()
+        .map(|(id, shortcut)| (shortcut, Action::Extension(id.clone())));
+
+    let mut bindings = HashMap::new();
+    for (shortcut_str, action) in main_wheel
+        .into_iter()
+        .chain(wheels)
+        .chain(palette)
+        .chain(hotkeys)
+    {
+        let shortcut = Shortcut::from_str(shortcut_str)?;
+        if let Some(existing) = bindings.insert(shortcut, action.clone()) {
+            return Err(Error::Shortcut(format!(
+                "'{shortcut_str}' is bound to both {existing} and {action}"
+            )));
+        }
+    }
+
+    Ok(bindings)
+}
+
+/// Returns the action bound to `shortcut`
+pub fn action(config: &Config, shortcut: &Shortcut) -> Option<Action> {
+    bindings(config).ok()?.remove(shortcut)
+}
+
+/// Registers all shortcuts of `config`
+pub fn register(app: &AppHandle, config: &Config) -> error::Result<()> {
+    for shortcut in bindings(config)?.into_keys() {
+        app.global_shortcut().register(shortcut)?;
+    }
+    Ok(())
+}
+
+/// Changes the registered shortcuts from the ones of `old` to the ones of `new`.
+///
+/// New shortcuts are registered first, so a failure leaves the old ones active.
+pub fn sync(app: &AppHandle, old: &Config, new: &Config) -> error::Result<()> {
+    let old = bindings(old).unwrap_or_default();
+    let new = bindings(new)?;
+
+    let added: Vec<Shortcut> = new
+        .keys()
+        .filter(|s| !old.contains_key(s))
+        .copied()
+        .collect();
+    for (i, shortcut) in added.iter().enumerate() {
+        if let Err(e) = app.global_shortcut().register(*shortcut) {
+            for registered in &added[..i] {
+                let _ = app.global_shortcut().unregister(*registered);
+            }
+            return Err(e.into());
+        }
+    }
+
+    // Now that the new shortcuts are live, remove the old registrations
+    for shortcut in old.keys().filter(|s| !new.contains_key(s)) {
+        app.global_shortcut().unregister(*shortcut)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Wheel;
+
+    use super::*;
+
+    fn shortcut(s: &str) -> Shortcut {
+        s.parse().unwrap()
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        let profile = config.profile_mut();
+        profile.shortcut = "Control+Shift+Space".into();
+        profile.palette_shortcut = Some("Control+Shift+P".into());
+        profile.hotkeys.insert("math".into(), "Alt+M".into());
+        profile.wheels.insert(
+            "tools".into(),
+            Wheel {
+                shortcut: "Control+Shift+T".into(),
+                extensions: Vec::new(),
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn every_shortcut_of_the_profile_is_bound() {
+        let config = config();
+        let bindings = bindings(&config).unwrap();
+        assert_eq!(bindings.len(), 4);
+
+        assert_eq!(
+            action(&config, &shortcut("Control+Shift+Space")),
+            Some(Action::Wheel(None))
+        );
+        assert_eq!(
+            action(&config, &shortcut("Control+Shift+T")),
+            Some(Action::Wheel(Some("tools".into())))
+        );
+        assert_eq!(
+            action(&config, &shortcut("Control+Shift+P")),
+            Some(Action::Palette)
+        );
+        assert_eq!(
+            action(&config, &shortcut("Alt+M")),
+            Some(Action::Extension("math".into()))
+        );
+        assert_eq!(action(&config, &shortcut("Alt+N")), None);
+    }
+
+    #[test]
+    fn shortcuts_bound_twice_are_rejected() {
+        let mut config = config();
+        config
+            .profile_mut()
+            .hotkeys
+            .insert("qrcode".into(), "control+shift+p".into());
+
+        let Err(Error::Shortcut(message)) = bindings(&config) else {
+            panic!("the shortcut is bound twice");
+        };
+        assert!(message.contains("the palette"), "{message}");
+        assert!(message.contains("extension 'qrcode'"), "{message}");
+        // Nothing is triggered while the bindings are invalid
+        assert_eq!(action(&config, &shortcut("Alt+M")), None);
+    }
+
+    #[test]
+    fn invalid_shortcuts_are_rejected() {
+        let mut config = config();
+        config.profile_mut().shortcut = "Control+Nope".into();
+        assert!(matches!(bindings(&config), Err(Error::Shortcut(_))));
+    }
+}
@@ -0,0 +1,82 @@
+use tauri::{
+    menu::{CheckMenuItemBuilder, Menu, MenuBuilder, MenuItemBuilder},
+    tray::TrayIconBuilder,
+    AppHandle, Emitter, Manager,
+};
+use tracing::warn;
+
+use crate::{config::Config, profiles::activate_profile, AppState, PKG_NAME, PKG_VERSION};
+
+pub const TRAY_ID: &str = "main_tray";
+
+/// Prefix of the menu item ids switching to a profile
+const PROFILE_PREFIX: &str = "profile:";
+
+/// Creates the system tray
+pub fn create(app: &AppHandle, config: &Config) -> tauri::Result<()> {
+    TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&build_menu(app, config)?)
+        .icon(app.default_window_icon().unwrap().clone())
+        .on_menu_event(move |app, event| {
+            let id = event.id.as_ref();
+            if id == "settings" {
+                if let Err(e) = show_settings(app) {
+                    warn!(error = %e, "failed to open settings");
+                }
+            } else if let Some(name) = id.strip_prefix(PROFILE_PREFIX) {
+                let result = app
+                    .state::<AppState>()
+                    .config
+                    .update(|config| activate_profile(config, name));
+                if let Err(e) = result {
+                    warn!(error = %e, profile = name, "failed to switch profile");
+
+                    // Undo the toggled check mark
+                    if let Ok(config) = app.state::<AppState>().config.read() {
+                        let _ = refresh(app, &config);
+                    }
+                }
+            }
+        })
+        .build(app)?;
+
+    Ok(())
+}
+
+/// Shows and focuses the settings window
+pub fn show_settings(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(settings_window) = app.get_webview_window("settings") {
+        settings_window.show()?;
+        settings_window.set_focus()?;
+        settings_window.emit("open-settings", ())?;
+    }
+    Ok(())
+}
+
+/// Rebuilds the tray menu, e.g. after the profiles changed
+pub fn refresh(app: &AppHandle, config: &Config) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(build_menu(app, config)?))?;
+    }
+    Ok(())
+}
+
+fn build_menu(app: &AppHandle, config: &Config) -> tauri::Result<Menu<tauri::Wry>> {
+    let version = MenuItemBuilder::new(format!("{PKG_NAME} {PKG_VERSION}"))
+        .enabled(false)
+        .build(app)?;
+    let settings = MenuItemBuilder::new("Settings")
+        .id("settings")
+        .accelerator("CmdOrCtrl+,")
+        .build(app)?;
+
+    let mut menu = MenuBuilder::new(app).item(&version).separator();
+    for name in config.profiles.keys() {
+        let profile = CheckMenuItemBuilder::with_id(format!("{PROFILE_PREFIX}{name}"), name)
+            .checked(*name == config.active_profile)
+            .build(app)?;
+        menu = menu.item(&profile);
+    }
+
+    menu.separator().item(&settings).separator().quit().build()
+}
@@ -69,7 +69,7 @@ pub async fn update_extensions(app: AppHandle) -> error::Result<()> {
                 return Ok(());
             }
 
-            let bytes = download_extension(&latest).await?;
+            let bytes = download_extension(&latest.assets).await?;
 
             match install_extension(&extension.manifest.id, bytes, state).await {
                 Ok(()) => info!(id = %extension.manifest.id, "installed extension update"),
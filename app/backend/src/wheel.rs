@@ -0,0 +1,339 @@
+use std::{
+    f64::consts::TAU,
+    time::{Duration, Instant},
+};
+
+use tauri::{
+    AppHandle, Emitter, LogicalSize, Manager, Monitor, PhysicalPosition, State, WebviewWindow,
+};
+use tauri_plugin_global_shortcut::ShortcutState;
+use tracing::warn;
+
+use crate::{
+    config::{Activation, ActivationMode},
+    error::{self, Error},
+    extensions::{emit_extensions_update, get_wheel_entries, WheelEntry},
+    geometry::{monitor_at, wheel_position, MonitorArea, Rect},
+    runs, AppState,
+};
+
+/// Maximum time between the two presses of a double tap
+const DOUBLE_TAP_INTERVAL: Duration = Duration::from_millis(300);
+
+/// State of the wheel between shortcut presses
+#[derive(Default)]
+pub struct WheelState {
+    /// Name of the wheel shown last, `None` for the main wheel of the profile
+    pub active: Option<String>,
+    /// Ids of the groups opened on top of the wheel, innermost last
+    pub open_groups: Vec<String>,
+    /// Where the wheel was opened, `None` while it is hidden
+    pub origin: Option<Origin>,
+    /// Time of the last shortcut press, for detecting double taps
+    pub last_press: Option<Instant>,
+}
+
+/// Cursor position the wheel was opened at
+#[derive(Clone, Copy)]
+pub struct Origin {
+    /// Position in physical pixels
+    pub position: (f64, f64),
+    /// Scale factor of the monitor under the cursor
+    pub scale_factor: f64,
+}
+
+/// Opens the wheel `wheel` or selects in the open one, depending on the activation mode
+pub fn on_shortcut(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    wheel: Option<String>,
+    state: ShortcutState,
+) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+    let activation = app_state.config.read()?.activation.clone();
+
+    match (activation.mode, state) {
+        (ActivationMode::HoldRelease, ShortcutState::Pressed) => show(app, window, wheel),
+        (ActivationMode::HoldRelease, ShortcutState::Released) => select(app, window, &activation),
+        (_, ShortcutState::Pressed) if window.is_visible()? => select(app, window, &activation),
+        (ActivationMode::Toggle, ShortcutState::Pressed) => show(app, window, wheel),
+        (ActivationMode::DoubleTap, ShortcutState::Pressed) => {
+            let now = Instant::now();
+            let double_tap = {
+                let mut state = app_state.wheel.lock()?;
+                let double_tap = state
+                    .last_press
+                    .is_some_and(|t| now - t <= DOUBLE_TAP_INTERVAL);
+                state.last_press = if double_tap { None } else { Some(now) };
+                double_tap
+            };
+
+            if double_tap {
+                show(app, window, wheel)?;
+            }
+            Ok(())
+        }
+        (_, ShortcutState::Released) => Ok(()),
+    }
+}
+
+/// Shows the wheel `wheel` centered around the cursor, within the work area of its monitor.
+/// If the cursor can't be read, the wheel is shown in the centre of the screen instead.
+pub fn show(app: &AppHandle, window: &WebviewWindow, wheel: Option<String>) -> error::Result<()> {
+    let (cursor, monitor) = cursor_monitor(app)?;
+
+    // switch the wheel's extensions if another one is shown
+    let app_state = app.state::<AppState>();
+    let changed = {
+        let mut state = app_state.wheel.lock()?;
+        let changed = state.active != wheel;
+        state.active = wheel;
+        state.open_groups.clear();
+        state.origin = cursor.map(|position| Origin {
+            position,
+            scale_factor: monitor.scale_factor,
+        });
+        changed
+    };
+    if changed {
+        emit_extensions_update(app)?;
+    }
+    window.emit("groups-changed", Vec::<String>::new())?;
+
+    // The logical size stays the same across monitors, the physical one doesn't
+    let size: LogicalSize<f64> = window.outer_size()?.to_logical(window.scale_factor()?);
+    let point = cursor.unwrap_or_else(|| monitor.work_area.centre());
+    let (x, y) = wheel_position(point, (size.width, size.height), &monitor);
+
+    window.set_position(PhysicalPosition::new(x.round() as i32, y.round() as i32))?;
+    window.show()?;
+    window.set_focus()?;
+    Ok(())
+}
+
+/// Selects the entry the cursor moved towards since the wheel was opened.
+/// Extensions are run and hide the wheel, groups are opened in place.
+pub fn select(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    activation: &Activation,
+) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+
+    let Some(origin) = app_state.wheel.lock()?.origin else {
+        return cancel(app, window);
+    };
+    let Ok(cursor) = app.cursor_position() else {
+        return cancel(app, window);
+    };
+    // In logical pixels, like the dead zone
+    let dx = (cursor.x - origin.position.0) / origin.scale_factor;
+    let dy = (cursor.y - origin.position.1) / origin.scale_factor;
+
+    let mut entries = open_entries(&app_state)?;
+    let Some(slot) = slot_at(entries.len(), dx, dy, activation.dead_zone) else {
+        return cancel(app, window);
+    };
+
+    match entries.swap_remove(slot) {
+        WheelEntry::Extension(extension) => {
+            cancel(app, window)?;
+            if let Err(e) = runs::start(app, extension.manifest.id.clone()) {
+                warn!(error = %e, id = %extension.manifest.id, "extension run failed");
+            }
+            Ok(())
+        }
+        WheelEntry::Group(group) => push_group(app, window, group.id),
+    }
+}
+
+/// Hides the wheel without running anything
+pub fn cancel(app: &AppHandle, window: &WebviewWindow) -> error::Result<()> {
+    {
+        let app_state = app.state::<AppState>();
+        let mut state = app_state.wheel.lock()?;
+        state.open_groups.clear();
+        state.origin = None;
+    }
+
+    window.hide()?;
+    window.emit("groups-changed", Vec::<String>::new())?;
+    Ok(())
+}
+
+/// Returns the index of the slot in direction (`dx`, `dy`) of the wheel's centre,
+/// none within `dead_zone` of it, as releasing in the centre cancels.
+///
+/// Slots are laid out like in the frontend: clockwise, starting at the top,
+/// each covering an equal part of the circle.
+pub fn slot_at(count: usize, dx: f64, dy: f64, dead_zone: f64) -> Option<usize> {
+    if count == 0 || dx.hypot(dy) < dead_zone {
+        return None;
+    }
+
+    // Angle clockwise from the top, as the y axis points down
+    let angle = (dx.atan2(-dy)).rem_euclid(TAU);
+    let step = TAU / count as f64;
+
+    Some((angle / step).round() as usize % count)
+}
+
+/// Returns the cursor position, if it can be read, and the monitor under it.
+///
+/// The monitor is looked up every time, as they may differ in scale.
+/// Without a cursor position, e.g. on Wayland, the primary monitor is used.
+pub fn cursor_monitor(app: &AppHandle) -> error::Result<(Option<(f64, f64)>, MonitorArea)> {
+    let cursor = app.cursor_position().ok().map(|c| (c.x, c.y));
+
+    let monitors: Vec<MonitorArea> = app.available_monitors()?.iter().map(monitor_area).collect();
+    let monitor = match cursor {
+        Some(cursor) => monitor_at(&monitors, cursor).copied(),
+        None => app.primary_monitor()?.as_ref().map(monitor_area),
+    };
+    let monitor = monitor
+        .or_else(|| monitors.first().copied())
+        .ok_or_else(|| Error::Tauri("no monitor found".into()))?;
+
+    Ok((cursor, monitor))
+}
+
+/// Converts a monitor into the area used for positioning windows
+pub fn monitor_area(monitor: &Monitor) -> MonitorArea {
+    let (position, size) = (monitor.position(), monitor.size());
+    let work_area = monitor.work_area();
+    MonitorArea {
+        bounds: Rect::new(
+            position.x as f64,
+            position.y as f64,
+            size.width as f64,
+            size.height as f64,
+        ),
+        work_area: Rect::new(
+            work_area.position.x as f64,
+            work_area.position.y as f64,
+            work_area.size.width as f64,
+            work_area.size.height as f64,
+        ),
+        scale_factor: monitor.scale_factor(),
+    }
+}
+
+/// Returns the entries shown in the innermost open group, or the wheel itself
+fn open_entries(app_state: &State<'_, AppState>) -> error::Result<Vec<WheelEntry>> {
+    let open_groups = app_state.wheel.lock()?.open_groups.clone();
+
+    let mut entries = get_wheel_entries(app_state.clone())?;
+    for id in &open_groups {
+        let group = entries.into_iter().find_map(|e| match e {
+            WheelEntry::Group(group) if &group.id == id => Some(group),
+            _ => None,
+        });
+        entries = group
+            .ok_or_else(|| Error::Wheel(format!("group '{id}' is not open")))?
+            .entries;
+    }
+
+    Ok(entries)
+}
+
+fn push_group(app: &AppHandle, window: &WebviewWindow, id: String) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+    let open_groups = {
+        let mut state = app_state.wheel.lock()?;
+        state.open_groups.push(id);
+        state.open_groups.clone()
+    };
+    window.emit("groups-changed", open_groups)?;
+    Ok(())
+}
+
+/// Opens the group `id` of the entries currently shown
+#[tauri::command]
+pub fn open_group(id: String, app: AppHandle, window: WebviewWindow) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+    let is_shown = open_entries(&app_state)?
+        .iter()
+        .any(|e| matches!(e, WheelEntry::Group(group) if group.id == id));
+    if !is_shown {
+        return Err(Error::Wheel(format!("group '{id}' is not shown")));
+    }
+
+    push_group(&app, &window, id)
+}
+
+/// Selects slot `index` of the entries currently shown, e.g. from a number key
+#[tauri::command]
+pub fn select_slot(index: usize, app: AppHandle, window: WebviewWindow) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+    let mut entries = open_entries(&app_state)?;
+    if index >= entries.len() {
+        return Err(Error::Wheel(format!("slot {} does not exist", index + 1)));
+    }
+
+    match entries.swap_remove(index) {
+        WheelEntry::Extension(extension) => {
+            cancel(&app, &window)?;
+            runs::start(&app, extension.manifest.id)?;
+            Ok(())
+        }
+        WheelEntry::Group(group) => push_group(&app, &window, group.id),
+    }
+}
+
+/// Closes the innermost open group
+#[tauri::command]
+pub fn close_group(app: AppHandle, window: WebviewWindow) -> error::Result<()> {
+    let app_state = app.state::<AppState>();
+    let open_groups = {
+        let mut state = app_state.wheel.lock()?;
+        state.open_groups.pop();
+        state.open_groups.clone()
+    };
+    window.emit("groups-changed", open_groups)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Direction of slot `i` out of `count`, placed like the frontend does at `angleStep * i - 90`
+    fn slot_direction(count: usize, i: usize, offset: f64) -> (f64, f64) {
+        let angle = (360. / count as f64 * i as f64 - 90. + offset).to_radians();
+        (angle.cos() * 100., angle.sin() * 100.)
+    }
+
+    #[test]
+    fn slots_are_laid_out_clockwise_from_the_top() {
+        let directions = [(0., -100.), (100., 0.), (0., 100.), (-100., 0.)];
+        for (slot, (dx, dy)) in directions.into_iter().enumerate() {
+            assert_eq!(slot_at(4, dx, dy, 16.), Some(slot), "({dx}, {dy})");
+        }
+    }
+
+    #[test]
+    fn slots_match_the_frontend() {
+        for count in 1..=12 {
+            // Anywhere up to half a step away from the slot still selects it
+            let within = 180. / count as f64 - 1.;
+            for i in 0..count {
+                for offset in [-within, 0., within] {
+                    let (dx, dy) = slot_direction(count, i, offset);
+                    assert_eq!(
+                        slot_at(count, dx, dy, 16.),
+                        Some(i),
+                        "{i} of {count} at {offset}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn centre_and_empty_wheels_select_nothing() {
+        assert_eq!(slot_at(4, 0., 0., 16.), None);
+        assert_eq!(slot_at(4, 10., -10., 16.), None);
+        assert_eq!(slot_at(4, 0., -16., 16.), Some(0));
+        assert_eq!(slot_at(0, 0., -100., 16.), None);
+    }
+}
@@ -5,6 +5,8 @@ namespace api {
 		| { kind: 'PoisonedLock' }
 		| { kind: 'Checksum' }
 		| { kind: 'NoAssets' }
+		| { kind: 'InvalidId'; value: string }
+		| { kind: 'UnknownRelease'; value: string }
 		| { kind: 'FileSystem'; value: string }
 		| { kind: 'LibLoading'; value: string }
 		| { kind: 'Conversion'; value: string }
@@ -12,7 +14,18 @@ namespace api {
 		| { kind: 'Reqwest'; value: string }
 		| { kind: 'Shortcut'; value: string }
 		| { kind: 'Autostart'; value: string }
-		| { kind: 'Tauri'; value: string };
+		| { kind: 'Tauri'; value: string }
+		| { kind: 'Lockfile'; value: string }
+		| { kind: 'PermissionsChanged'; value: string }
+		| { kind: 'StaleRevision'; value: number }
+		| { kind: 'Profile'; value: string }
+		| { kind: 'Wheel'; value: string }
+		| { kind: 'Launch'; value: string }
+		| { kind: 'Run'; value: string }
+		| { kind: 'Cancelled' }
+		| { kind: 'TimedOut'; value: number }
+		| { kind: 'Crashed'; value: string }
+		| { kind: 'LimitExceeded'; value: string };
 
 	export interface ExtensionManifest {
 		id: string;
@@ -21,8 +34,23 @@ namespace api {
 		version: string;
 		description: string;
 		latest_url: string;
+		keywords: string[];
+		permissions: Permission[];
+		kind: ExtensionKind;
 	}
 
+	/** A native library per platform or a WebAssembly component running everywhere */
+	export type ExtensionKind = 'native' | 'wasm';
+
+	/** A capability declared in the manifest, approved by the user */
+	export type Permission =
+		| 'clipboard-read'
+		| 'clipboard-write'
+		| 'screen-capture'
+		| 'network'
+		| 'simulate-input'
+		| { filesystem: string };
+
 	export interface AvailableExtension {
 		id: string;
 		name: string;
@@ -35,13 +63,120 @@ namespace api {
 		manifest: ExtensionManifest;
 		icon_path: string;
 		enabled: boolean;
+		pending_permissions: Permission[];
 	}
 
 	export interface Config {
+		revision: number;
 		autolaunch: boolean;
+		activation: Activation;
+		run_timeout: number;
+		memory_limit: number;
+		limits: Record<string, RunLimits>;
+		/** Reasons of extensions disabled after failing repeatedly, by id */
+		quarantined: Record<string, string>;
+		registry: string;
+		developer_console: boolean;
+		active_profile: string;
+		profiles: Record<string, Profile>;
+	}
+
+	/** Overrides of the defaults in seconds and MiB, unset ones use the defaults */
+	export interface RunLimits {
+		wall_time: number | null;
+		cpu_time: number | null;
+		memory: number | null;
+	}
+
+	export type RunState =
+		| { kind: 'Started' }
+		| { kind: 'Progress'; value: { fraction: number; message: string } }
+		| { kind: 'Finished' }
+		| { kind: 'Failed'; value: Error };
+
+	/** Payload of the `run-update` event */
+	export interface RunUpdate {
+		run_id: number;
+		id: string;
+		state: RunState;
+	}
+
+	/** A line an extension wrote to stdout or stderr */
+	export interface OutputLine {
+		/** Milliseconds since the start of the run */
+		at_ms: number;
+		stream: 'stdout' | 'stderr';
+		text: string;
+	}
+
+	/** A finished run, shown in the developer console */
+	export interface RunRecord {
+		run_id: number;
+		id: string;
+		/** Milliseconds since the unix epoch */
+		started_at: number;
+		duration_ms: number;
+		output: OutputLine[];
+		error: Error | null;
+		/** Whether it ran in a runner process, only then is its output captured (unix) */
+		isolated: boolean;
+	}
+
+	/** Payload of the `extension-notification` event */
+	export interface ExtensionNotification {
+		id: string;
+		title: string;
+		body: string;
+	}
+
+	export interface Activation {
+		mode: ActivationMode;
+		dead_zone: number;
+	}
+
+	export type ActivationMode = 'hold_release' | 'toggle' | 'double_tap';
+
+	export interface Profile {
 		shortcut: string;
 		enabled: string[];
 		ordered: string[];
+		palette_shortcut: string | null;
+		hotkeys: Record<string, string>;
+		wheels: Record<string, Wheel>;
+		groups: Record<string, Group>;
+	}
+
+	export interface Group {
+		name: string;
+		entries: string[];
+	}
+
+	export type WheelEntry =
+		| { kind: 'Extension'; value: InstalledExtensionInfo }
+		| { kind: 'Group'; value: GroupEntry };
+
+	export interface GroupEntry {
+		id: string;
+		name: string;
+		entries: WheelEntry[];
+	}
+
+	export interface Wheel {
+		shortcut: string;
+		extensions: string[];
+	}
+
+	export interface ImportReport {
+		install: string[];
+		update: string[];
+		extra: string[];
+		config_changed: boolean;
+	}
+
+	export interface LockDrift {
+		missing: string[];
+		mismatched: string[];
+		extra: string[];
 	}
 
 	export async function get_version(): Promise<string> {
@@ -52,16 +187,54 @@ namespace api {
 		return await invoke('get_installed_extensions');
 	}
 
+	export async function get_wheel_entries(): Promise<WheelEntry[]> {
+		return await invoke('get_wheel_entries');
+	}
+
+	export async function open_group(id: string): Promise<void> {
+		return await invoke('open_group', { id });
+	}
+
+	export async function close_group(): Promise<void> {
+		return await invoke('close_group');
+	}
+
+	export async function select_slot(index: number): Promise<void> {
+		return await invoke('select_slot', { index });
+	}
+
+	export async function search_palette(query: string): Promise<InstalledExtensionInfo[]> {
+		return await invoke('search_palette', { query });
+	}
+
 	export async function fetch_online_extensions(): Promise<AvailableExtension[]> {
 		return await invoke('fetch_online_extensions');
 	}
 
-	export async function run_extension(extension_name: string): Promise<void> {
+	export async function run_extension(extension_name: string): Promise<number> {
 		return await invoke('run_extension', {
 			extensionName: extension_name
 		});
 	}
 
+	export async function cancel_extension_run(run_id: number): Promise<void> {
+		return await invoke('cancel_extension_run', { runId: run_id });
+	}
+
+	/** Recent runs for the developer console, newest first */
+	export async function get_run_records(): Promise<RunRecord[]> {
+		return await invoke('get_run_records');
+	}
+
+	export async function clear_run_records(): Promise<void> {
+		return await invoke('clear_run_records');
+	}
+
+	/** Recent log output of an extension, oldest line first */
+	export async function get_extension_logs(id: string): Promise<string> {
+		return await invoke('get_extension_logs', { id });
+	}
+
 	export async function download_and_install_extension(
 		id: string,
 		latest_url: string
@@ -72,9 +245,16 @@ namespace api {
 		});
 	}
 
-	export async function delete_extension(id: string): Promise<void> {
+	/** Approves the reviewed `permissions`, fails if the extension declares others by now */
+	export async function grant_permissions(id: string, permissions: Permission[]): Promise<void> {
+		return await invoke('grant_permissions', { id, permissions });
+	}
+
+	/** Removes an extension, its own data is only wiped if `keep_data` isn't set */
+	export async function delete_extension(id: string, keep_data: boolean): Promise<void> {
 		return await invoke('delete_extension', {
-			id: id
+			id: id,
+			keepData: keep_data
 		});
 	}
 
@@ -94,6 +274,108 @@ namespace api {
 		return await invoke('change_config', { newConfig: new_config });
 	}
 
+	export async function set_extension_enabled(
+		id: string,
+		enabled: boolean,
+		revision: number
+	): Promise<Config> {
+		return await invoke('set_extension_enabled', { id, enabled, revision });
+	}
+
+	export async function move_extension(
+		id: string,
+		index: number,
+		revision: number
+	): Promise<Config> {
+		return await invoke('move_extension', { id, index, revision });
+	}
+
+	export async function set_shortcut(shortcut: string, revision: number): Promise<Config> {
+		return await invoke('set_shortcut', { shortcut, revision });
+	}
+
+	export async function set_palette_shortcut(
+		shortcut: string | null,
+		revision: number
+	): Promise<Config> {
+		return await invoke('set_palette_shortcut', { shortcut, revision });
+	}
+
+	export async function set_extension_shortcut(
+		id: string,
+		shortcut: string | null,
+		revision: number
+	): Promise<Config> {
+		return await invoke('set_extension_shortcut', { id, shortcut, revision });
+	}
+
+	export async function set_wheel(
+		name: string,
+		wheel: Wheel | null,
+		revision: number
+	): Promise<Config> {
+		return await invoke('set_wheel', { name, wheel, revision });
+	}
+
+	export async function set_group(
+		id: string,
+		group: Group | null,
+		revision: number
+	): Promise<Config> {
+		return await invoke('set_group', { id, group, revision });
+	}
+
+	export async function set_activation(
+		activation: Activation,
+		revision: number
+	): Promise<Config> {
+		return await invoke('set_activation', { activation, revision });
+	}
+
+	export async function set_autolaunch(autolaunch: boolean, revision: number): Promise<Config> {
+		return await invoke('set_autolaunch', { autolaunch, revision });
+	}
+
+	export async function create_profile(name: string, revision: number): Promise<Config> {
+		return await invoke('create_profile', { name, revision });
+	}
+
+	export async function rename_profile(
+		name: string,
+		new_name: string,
+		revision: number
+	): Promise<Config> {
+		return await invoke('rename_profile', { name, newName: new_name, revision });
+	}
+
+	export async function delete_profile(name: string, revision: number): Promise<Config> {
+		return await invoke('delete_profile', { name, revision });
+	}
+
+	export async function switch_profile(name: string, revision: number): Promise<Config> {
+		return await invoke('switch_profile', { name, revision });
+	}
+
+	export async function export_setup(path: string): Promise<void> {
+		return await invoke('export_setup', { path });
+	}
+
+	export async function import_setup(path: string, dry_run: boolean): Promise<ImportReport> {
+		return await invoke('import_setup', { path, dryRun: dry_run });
+	}
+
+	export async function write_lockfile(path: string): Promise<void> {
+		return await invoke('write_lockfile', { path });
+	}
+
+	export async function lockfile_drift(path: string): Promise<LockDrift> {
+		return await invoke('lockfile_drift', { path });
+	}
+
+	export async function sync_lockfile(path: string): Promise<LockDrift> {
+		return await invoke('sync_lockfile', { path });
+	}
+
 	export async function read_to_string(path: string): Promise<string> {
 		return await invoke('read_to_string', { path: path });
 	}
@@ -26,7 +26,7 @@ export function error_toast(e: { title: string; description: string }) {
 }
 
 /** Server Error translations */
-function error_msg(error: api.Error): { title: string; description: string } {
+export function error_msg(error: api.Error): { title: string; description: string } {
 	switch (error.kind) {
 		case 'PoisonedLock':
 			return { title: 'Fatal Backend Error', description: 'An internal lock was poisoned.' };
@@ -34,6 +34,10 @@ function error_msg(error: api.Error): { title: string; description: string } {
 			return { title: 'Verification Error', description: 'The checksum verification failed.' };
 		case 'NoAssets':
 			return { title: 'Error', description: 'No assets found for this platform.' };
+		case 'InvalidId':
+			return { title: 'Error', description: `Invalid extension id '${error.value}'.` };
+		case 'UnknownRelease':
+			return { title: 'Error', description: `Release ${error.value} not found.` };
 		case 'FileSystem':
 			return { title: 'File System Error', description: error.value };
 		case 'LibLoading':
@@ -50,6 +54,40 @@ function error_msg(error: api.Error): { title: string; description: string } {
 			return { title: 'Global Shortcut Error', description: error.value };
 		case 'Autostart':
 			return { title: 'Autostart Configuration Error', description: error.value };
+		case 'Lockfile':
+			return { title: 'Lockfile Error', description: error.value };
+		case 'PermissionsChanged':
+			return {
+				title: 'Permissions Changed',
+				description: `'${error.value}' asks for other permissions by now, review them again.`
+			};
+		case 'StaleRevision':
+			return {
+				title: 'Outdated Settings',
+				description: 'The settings were changed elsewhere. Reopen them and try again.'
+			};
+		case 'Profile':
+			return { title: 'Profile Error', description: error.value };
+		case 'Wheel':
+			return { title: 'Wheel Error', description: error.value };
+		case 'Launch':
+			return { title: 'Launch Error', description: error.value };
+		case 'Run':
+			return { title: 'Extension Run Error', description: error.value };
+		case 'Cancelled':
+			return { title: 'Cancelled', description: 'The extension run was cancelled.' };
+		case 'TimedOut':
+			return {
+				title: 'Timed Out',
+				description: `The extension didn't finish within ${error.value} seconds.`
+			};
+		case 'Crashed':
+			return { title: 'Extension Crashed', description: error.value };
+		case 'LimitExceeded':
+			return {
+				title: 'Limit Exceeded',
+				description: `The extension exceeded its limit of ${error.value}.`
+			};
 		default:
 			return {
 				title: 'Fatal Frontend Error',
@@ -1,3 +1,5 @@
+import type api from './api';
+
 /** Function deep-cloning objects with arrays */
 export function deepClone(obj: any): any {
 	if (obj === null || typeof obj !== 'object') return obj;
@@ -45,3 +47,10 @@ export function areObjectsEqual(obj1: any, obj2: any): boolean {
 
 	return true;
 }
+
+/** Collects the extensions of wheel entries, including the ones inside groups */
+export function wheelExtensions(entries: api.WheelEntry[]): api.InstalledExtensionInfo[] {
+	return entries.flatMap((entry) =>
+		entry.kind === 'Extension' ? [entry.value] : wheelExtensions(entry.value.entries)
+	);
+}
@@ -5,9 +5,15 @@ extension_entry!(main);
 
 // Add now here your extension code
 // Use `pointy_api` for bundled dependencies and helper functions for the clipboard
+// Long running work should check `pointy_api::is_cancelled()` and return early,
+// `pointy_api::report_progress` and `pointy_api::notify` keep the user informed.
+// Log with `pointy_api::info!` and friends, the output is shown in the settings
+// State between runs can be kept with `pointy_api::storage` or in `pointy_api::data_dir()`
+// Capabilities like `"clipboard-read"` have to be declared under `permissions` in the manifest
+// A WebAssembly component of `pointy_runner/wit/extension.wit` with `"kind": "wasm"` runs everywhere
 fn main() -> Result<(), String> {
-    // Some sample print
-    println!("Hello World!");
+    // Some sample log
+    pointy_api::info!("Hello World!");
 
     Ok(())
 }
@@ -0,0 +1,226 @@
+//! Connection to the host running the extension, set up by [`extension_entry`](crate::extension_entry).
+
+use std::{
+    cell::Cell,
+    ffi::{c_void, CStr, CString},
+    os::raw::c_char,
+    panic,
+    path::PathBuf,
+    ptr, slice,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use image::RgbaImage;
+
+/// Version of the [`HostContext`] layout, increased whenever fields are appended.
+pub const HOST_CONTEXT_VERSION: u32 = 6;
+
+/// Passed by the host to every run. Fields are only ever appended,
+/// so a field may only be read if `version` is at least the one that added it.
+#[repr(C)]
+pub struct HostContext {
+    /// The [`HOST_CONTEXT_VERSION`] of the host
+    pub version: u32,
+    /// Set by the host once the run should stop
+    pub cancelled: *const AtomicBool,
+    /// Passed back to the callbacks, since version 2
+    pub data: *mut c_void,
+    /// Shows the progress of the run, since version 2
+    pub report_progress: extern "C" fn(data: *mut c_void, fraction: f32, message: *const c_char),
+    /// Shows a notification to the user, since version 2
+    pub notify: extern "C" fn(data: *mut c_void, title: *const c_char, body: *const c_char),
+    /// Writes to the log of the extension, `level` being a [`Level`], since version 3
+    pub log: extern "C" fn(data: *mut c_void, level: u32, message: *const c_char),
+    /// Directory of the extension's own data, created by the host, since version 4
+    pub data_dir: *const c_char,
+    /// JSON array of the [`Permission`](crate::Permission)s granted to the extension, since version 5
+    pub permissions: *const c_char,
+    /// Performs `call`, a [`HostCall`], if the extension was granted what it needs, since version 6.
+    /// Its output is passed to `write` along with `out`, on failure it is the error message.
+    /// Returns whether it succeeded.
+    pub call: extern "C" fn(
+        data: *mut c_void,
+        call: u32,
+        input: *const u8,
+        input_len: usize,
+        out: *mut c_void,
+        write: extern "C" fn(out: *mut c_void, bytes: *const u8, len: usize),
+    ) -> bool,
+}
+
+/// What an extension can ask the host for, each needing a [`Permission`](crate::Permission).
+///
+/// Text is passed as UTF-8, images as their width and height as little endian `u32`s
+/// followed by their RGBA pixels and HTML as a JSON array of the HTML and its alternative text.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostCall {
+    ClipboardGetText = 1,
+    ClipboardGetImage = 2,
+    ClipboardWriteText = 3,
+    ClipboardWriteHtml = 4,
+    ClipboardWriteImage = 5,
+    ClipboardClear = 6,
+    /// Of the monitor under the cursor
+    CaptureScreen = 7,
+}
+
+impl HostCall {
+    pub fn from_u32(call: u32) -> Option<Self> {
+        Some(match call {
+            1 => HostCall::ClipboardGetText,
+            2 => HostCall::ClipboardGetImage,
+            3 => HostCall::ClipboardWriteText,
+            4 => HostCall::ClipboardWriteHtml,
+            5 => HostCall::ClipboardWriteImage,
+            6 => HostCall::ClipboardClear,
+            7 => HostCall::CaptureScreen,
+            _ => return None,
+        })
+    }
+}
+
+/// Severity of a log message, see [`info!`](crate::info) and friends
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+thread_local! {
+    static CONTEXT: Cell<*const HostContext> = const { Cell::new(ptr::null()) };
+}
+
+/// Calls `f` with the context of the current run, `None` if run by a host without one.
+pub(crate) fn with_context<T>(f: impl FnOnce(Option<&HostContext>) -> T) -> T {
+    // SAFETY: the host keeps the context alive until the run returns, afterwards it is reset
+    CONTEXT.with(|c| f(unsafe { c.get().as_ref() }))
+}
+
+/// Whether the host asked the run to stop, e.g. because it was cancelled or timed out.
+/// Long running extensions should check this regularly and return early.
+pub fn is_cancelled() -> bool {
+    with_context(|ctx| {
+        // SAFETY: the flag lives as long as the context
+        ctx.is_some_and(|ctx| unsafe { (*ctx.cancelled).load(Ordering::Relaxed) })
+    })
+}
+
+/// Reports how far the run got, `fraction` going from 0 to 1.
+/// The host shows it along with `message`, e.g. "Encoding image".
+pub fn report_progress(fraction: f32, message: &str) {
+    with_context(|ctx| {
+        if let Some(ctx) = ctx.filter(|ctx| ctx.version >= 2) {
+            let message = c_string(message);
+            (ctx.report_progress)(ctx.data, fraction.clamp(0., 1.), message.as_ptr());
+        }
+    })
+}
+
+/// Shows a notification to the user, e.g. once a long run is done.
+pub fn notify(title: &str, body: &str) {
+    with_context(|ctx| {
+        if let Some(ctx) = ctx.filter(|ctx| ctx.version >= 2) {
+            let (title, body) = (c_string(title), c_string(body));
+            (ctx.notify)(ctx.data, title.as_ptr(), body.as_ptr());
+        }
+    })
+}
+
+/// Writes `message` to the log of the extension, which the host keeps per extension.
+/// Without a host, e.g. in tests, it is printed to stderr.
+pub fn log(level: Level, message: &str) {
+    with_context(|ctx| match ctx.filter(|ctx| ctx.version >= 3) {
+        Some(ctx) => {
+            let message = c_string(message);
+            (ctx.log)(ctx.data, level as u32, message.as_ptr());
+        }
+        None => eprintln!("[{level:?}] {message}"),
+    })
+}
+
+/// Directory in which the extension can keep its data between runs.
+/// It is created by the host and kept on updates, see also [`storage`](crate::storage).
+pub fn data_dir() -> Result<PathBuf, String> {
+    with_context(|ctx| match ctx.filter(|ctx| ctx.version >= 4) {
+        // SAFETY: the host keeps the path alive during the run
+        Some(ctx) => Ok(PathBuf::from(
+            unsafe { CStr::from_ptr(ctx.data_dir) }
+                .to_string_lossy()
+                .into_owned(),
+        )),
+        None => Err("the host doesn't provide a data directory".into()),
+    })
+}
+
+/// Asks the host to perform `call`, failing with hosts too old for it
+pub(crate) fn call_host(call: HostCall, input: &[u8]) -> Result<Vec<u8>, String> {
+    extern "C" fn write(out: *mut c_void, bytes: *const u8, len: usize) {
+        // SAFETY: `out` is the buffer below and `bytes` valid for `len` bytes during the call
+        unsafe { (*(out as *mut Vec<u8>)).extend_from_slice(slice::from_raw_parts(bytes, len)) }
+    }
+
+    with_context(|ctx| {
+        let Some(ctx) = ctx.filter(|ctx| ctx.version >= 6) else {
+            return Err(format!(
+                "the host doesn't support {call:?}, it needs a newer pointy"
+            ));
+        };
+        let mut out = Vec::new();
+        let out_ptr = &mut out as *mut Vec<u8> as *mut c_void;
+        if (ctx.call)(
+            ctx.data,
+            call as u32,
+            input.as_ptr(),
+            input.len(),
+            out_ptr,
+            write,
+        ) {
+            Ok(out)
+        } else {
+            Err(String::from_utf8_lossy(&out).into_owned())
+        }
+    })
+}
+
+/// Encodes `image` for a [`HostCall`]
+pub fn image_to_bytes(image: &RgbaImage) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + image.as_raw().len());
+    bytes.extend_from_slice(&image.width().to_le_bytes());
+    bytes.extend_from_slice(&image.height().to_le_bytes());
+    bytes.extend_from_slice(image.as_raw());
+    bytes
+}
+
+/// Decodes an image of a [`HostCall`]
+pub fn image_from_bytes(mut bytes: Vec<u8>) -> Result<RgbaImage, String> {
+    let size = |at: usize| {
+        let le = bytes.get(at..at + 4)?.try_into().ok()?;
+        Some(u32::from_le_bytes(le))
+    };
+    let (Some(width), Some(height)) = (size(0), size(4)) else {
+        return Err("the image is missing its size".into());
+    };
+    let pixels = bytes.split_off(8);
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| "the image is smaller than its size".into())
+}
+
+/// Converts `text` for the host, dropping nul bytes which can't be represented
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap()
+}
+
+/// Runs `func` with the host's context, used by the generated entry points.
+#[doc(hidden)]
+pub fn run_entry(ctx: *const HostContext, func: fn() -> Result<(), String>) -> *mut c_char {
+    CONTEXT.with(|c| c.set(ctx));
+    // Unwinding into the host is undefined behaviour
+    let result = panic::catch_unwind(func).unwrap_or_else(|_| Err("extension panicked".into()));
+    CONTEXT.with(|c| c.set(ptr::null()));
+
+    c_string(&result.err().unwrap_or_default()).into_raw()
+}
@@ -5,15 +5,29 @@ pub use image;
 
 use std::borrow::Cow;
 
-use arboard::{Clipboard, ImageData};
+use arboard::ImageData;
+use host::{call_host, image_from_bytes, image_to_bytes};
 use image::RgbaImage;
 
-/// This macro generates the FFI function `run` which:
-/// - Executes your custom logic provided as a function that returns a `Result<(), String>`.
-/// - Converts that result into a `CString` (`*mut c_char`) for return.
+pub mod host;
+pub mod permissions;
+pub mod storage;
+
+pub use host::{
+    data_dir, is_cancelled, log, notify, report_progress, HostCall, HostContext, Level,
+};
+pub use permissions::{require, Permission};
+
+/// This macro generates the FFI functions `run` and `run_with_context` which:
+/// - Execute your custom logic provided as a function that returns a `Result<(), String>`.
+/// - Convert that result into a `CString` (`*mut c_char`) for return.
+///
+/// Hosts passing a [`HostContext`] call `run_with_context`, which makes functions
+/// like [`is_cancelled`] available during the run.
 /// ---
 /// Usage:
-/// ```rust
+/// ```rust,no_run
+/// # use pointy_api::{clipboard_get_text, clipboard_write_text, extension_entry};
 /// extension_entry!(main);
 ///
 /// fn main() -> Result<(), String> {
@@ -27,59 +41,97 @@ macro_rules! extension_entry {
     ($func:path) => {
         #[no_mangle]
         pub extern "C" fn run() -> *mut std::os::raw::c_char {
-            let result: Result<(), String> = $func();
-            match result {
-                Ok(()) => std::ffi::CString::new("").unwrap().into_raw(),
-                Err(e) => std::ffi::CString::new(e).unwrap().into_raw(),
-            }
+            $crate::host::run_entry(std::ptr::null(), $func)
         }
+
+        #[no_mangle]
+        pub extern "C" fn run_with_context(
+            ctx: *const $crate::HostContext,
+        ) -> *mut std::os::raw::c_char {
+            $crate::host::run_entry(ctx, $func)
+        }
+    };
+}
+
+/// Logs an error of the extension, formatted like [`format!`].
+/// The host writes it to the extension's log, which is shown in the settings.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log($crate::Level::Error, &format!($($arg)*))
+    };
+}
+
+/// Logs a warning of the extension, see [`error!`].
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log($crate::Level::Warn, &format!($($arg)*))
     };
 }
 
-/// Gets the text from the clipboard.
+/// Logs information about the extension, see [`error!`].
+/// ```rust,no_run
+/// # let words = 3;
+/// pointy_api::info!("counted {words} words");
+/// ```
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log($crate::Level::Info, &format!($($arg)*))
+    };
+}
+
+/// Logs details for debugging the extension, see [`error!`].
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log($crate::Level::Debug, &format!($($arg)*))
+    };
+}
+
+/// Gets the text from the clipboard, needs [`Permission::ClipboardRead`].
 pub fn clipboard_get_text() -> Result<String, String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.get_text().map_err(|e| e.to_string())
+    let bytes = call_host(HostCall::ClipboardGetText, &[])?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
 }
 
-/// Gets an image from the clipboard.
+/// Gets an image from the clipboard, needs [`Permission::ClipboardRead`].
 pub fn clipboard_get_image() -> Result<ImageData<'static>, String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.get_image().map_err(|e| e.to_string())
+    let image = image_from_bytes(call_host(HostCall::ClipboardGetImage, &[])?)?;
+    Ok(ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: Cow::Owned(image.into_raw()),
+    })
 }
 
-/// Writes text to the clipboard.
+/// Writes text to the clipboard, needs [`Permission::ClipboardWrite`].
 pub fn clipboard_write_text(text: String) -> Result<(), String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.set_text(text).map_err(|e| e.to_string())
+    call_host(HostCall::ClipboardWriteText, text.as_bytes()).map(drop)
 }
 
-/// Writes text to the clipboard.
+/// Writes HTML to the clipboard, needs [`Permission::ClipboardWrite`].
 pub fn clipboard_write_html(text: String, alt_text: Option<String>) -> Result<(), String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard
-        .set_html(text, alt_text)
-        .map_err(|e| e.to_string())
+    let input = serde_json::to_vec(&(text, alt_text)).map_err(|e| e.to_string())?;
+    call_host(HostCall::ClipboardWriteHtml, &input).map(drop)
 }
 
-/// Writes an image to the clipboard.
+/// Writes an image to the clipboard, needs [`Permission::ClipboardWrite`].
 pub fn clipboard_write_image(image_buffer: RgbaImage) -> Result<(), String> {
-    let bytes: Cow<'_, [u8]> = Cow::Borrowed(image_buffer.as_raw());
-    let width = image_buffer.width() as usize;
-    let height = image_buffer.height() as usize;
-
-    let image = ImageData {
-        width,
-        height,
-        bytes,
-    };
-
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.set_image(image).map_err(|e| e.to_string())
+    call_host(
+        HostCall::ClipboardWriteImage,
+        &image_to_bytes(&image_buffer),
+    )
+    .map(drop)
 }
 
-/// Clears the entire clipboard.
+/// Clears the entire clipboard, needs [`Permission::ClipboardWrite`].
 pub fn clipboard_clear() -> Result<(), String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    clipboard.clear().map_err(|e| e.to_string())
+    call_host(HostCall::ClipboardClear, &[]).map(drop)
+}
+
+/// Captures the monitor under the cursor, needs [`Permission::ScreenCapture`].
+pub fn capture_screen() -> Result<RgbaImage, String> {
+    image_from_bytes(call_host(HostCall::CaptureScreen, &[])?)
 }
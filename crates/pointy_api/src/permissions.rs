@@ -0,0 +1,125 @@
+//! Capabilities an extension declares in its manifest and the user approves on install.
+//!
+//! The host checks them whenever the clipboard helpers of this crate or [`capture_screen`]
+//! are called, native extensions doing the same on their own aren't stopped outside of
+//! the sandbox of their runner.
+//!
+//! [`capture_screen`]: crate::capture_screen
+
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::host::with_context;
+
+/// A capability, written like `"clipboard-read"` or `{ "filesystem": "~/Pictures" }` in the manifest
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Permission {
+    ClipboardRead,
+    ClipboardWrite,
+    ScreenCapture,
+    Network,
+    SimulateInput,
+    /// Access to a directory and everything within it, `~` being the home directory
+    Filesystem(PathBuf),
+}
+
+impl Permission {
+    /// Whether being granted `self` covers `other`, e.g. a directory covering its files
+    pub fn covers(&self, other: &Permission) -> bool {
+        match (self, other) {
+            (Permission::Filesystem(dir), Permission::Filesystem(path)) => {
+                // `..` could lead outside of the directory
+                !path.components().any(|c| c == Component::ParentDir) && path.starts_with(dir)
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permission::ClipboardRead => write!(f, "clipboard-read"),
+            Permission::ClipboardWrite => write!(f, "clipboard-write"),
+            Permission::ScreenCapture => write!(f, "screen-capture"),
+            Permission::Network => write!(f, "network"),
+            Permission::SimulateInput => write!(f, "simulate-input"),
+            Permission::Filesystem(path) => write!(f, "filesystem '{}'", path.display()),
+        }
+    }
+}
+
+/// Returns an error unless `permission` was granted to the extension.
+/// Without a host passing permissions, e.g. an older one, nothing is granted.
+pub fn require(permission: &Permission) -> Result<(), String> {
+    let granted = with_context(|ctx| ctx.and_then(granted)).unwrap_or_default();
+    check(&granted, permission)
+}
+
+/// Returns an error unless `permission` is covered by one of `granted`, as checked by hosts
+pub fn check(granted: &[Permission], permission: &Permission) -> Result<(), String> {
+    if granted.iter().any(|g| g.covers(permission)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "permission {permission} was not granted to this extension"
+        ))
+    }
+}
+
+/// Like [`require`] for accessing `path` outside of the [`data_dir`](crate::data_dir)
+pub fn require_path(path: &Path) -> Result<(), String> {
+    require(&Permission::Filesystem(path.to_path_buf()))
+}
+
+/// The permissions passed by the host, `None` if it doesn't pass any
+fn granted(ctx: &crate::HostContext) -> Option<Vec<Permission>> {
+    if ctx.version < 5 {
+        return None;
+    }
+    // SAFETY: the host keeps the string alive during the run
+    let json = unsafe { std::ffi::CStr::from_ptr(ctx.permissions) }.to_string_lossy();
+    // Unreadable ones grant nothing
+    Some(serde_json::from_str(&json).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_are_written_like_in_manifests() {
+        let json = r#"["clipboard-read", "network", {"filesystem": "~/Pictures"}]"#;
+        let permissions: Vec<Permission> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            permissions,
+            [
+                Permission::ClipboardRead,
+                Permission::Network,
+                Permission::Filesystem("~/Pictures".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn directories_cover_their_contents() {
+        let pictures = Permission::Filesystem("/home/me/Pictures".into());
+        assert!(pictures.covers(&Permission::Filesystem("/home/me/Pictures/a.png".into())));
+        assert!(!pictures.covers(&Permission::Filesystem("/home/me/Documents".into())));
+        assert!(!pictures.covers(&Permission::Filesystem("/home/me/Pictures2".into())));
+        assert!(!pictures.covers(&Permission::Filesystem("/home/me/Pictures/../.ssh".into())));
+        assert!(!Permission::ClipboardRead.covers(&Permission::ClipboardWrite));
+    }
+
+    #[test]
+    fn nothing_is_granted_without_a_host() {
+        assert!(require(&Permission::ClipboardRead).is_err());
+        assert!(crate::clipboard_get_text().is_err());
+        assert!(crate::capture_screen().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! A simple key-value store kept in the extension's [`data_dir`](crate::data_dir).
+//!
+//! Values are stored as JSON, so anything implementing serde's traits can be kept:
+//! ```rust,no_run
+//! let size: u32 = pointy_api::storage::get("qr_size")?.unwrap_or(256);
+//! pointy_api::storage::set("qr_size", &size)?;
+//! # Ok::<(), String>(())
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+/// File of the store within the data dir
+pub const STORE_FILE: &str = "store.json";
+
+fn store_path() -> Result<PathBuf, String> {
+    Ok(crate::data_dir()?.join(STORE_FILE))
+}
+
+/// Returns the value of `key`, `None` if it isn't set.
+pub fn get<T: DeserializeOwned>(key: &str) -> Result<Option<T>, String> {
+    get_in(&store_path()?, key)
+}
+
+/// Sets `key` to `value`, replacing the previous one.
+pub fn set<T: Serialize + ?Sized>(key: &str, value: &T) -> Result<(), String> {
+    set_in(&store_path()?, key, value)
+}
+
+/// Removes `key` and returns whether it was set.
+pub fn remove(key: &str) -> Result<bool, String> {
+    remove_in(&store_path()?, key)
+}
+
+fn load(path: &Path) -> Result<Map<String, Value>, String> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Map::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Writes to a temporary file first, so a crash can't leave a partial store behind
+fn save(path: &Path, store: &Map<String, Value>) -> Result<(), String> {
+    let json = serde_json::to_string(store).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Like [`get`] for the store at `path`, e.g. for hosts passing it on
+pub fn get_in<T: DeserializeOwned>(path: &Path, key: &str) -> Result<Option<T>, String> {
+    load(path)?
+        .remove(key)
+        .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// Like [`set`] for the store at `path`
+pub fn set_in<T: Serialize + ?Sized>(path: &Path, key: &str, value: &T) -> Result<(), String> {
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    let mut store = load(path)?;
+    store.insert(key.to_string(), value);
+    save(path, &store)
+}
+
+/// Like [`remove`] for the store at `path`
+pub fn remove_in(path: &Path, key: &str) -> Result<bool, String> {
+    let mut store = load(path)?;
+    let removed = store.remove(key).is_some();
+    if removed {
+        save(path, &store)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_kept() {
+        let dir = std::env::temp_dir().join(format!("pointy-storage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(STORE_FILE);
+
+        assert_eq!(get_in::<u32>(&path, "qr_size").unwrap(), None);
+        set_in(&path, "qr_size", &512).unwrap();
+        set_in(&path, "words", &["correct", "horse"]).unwrap();
+        assert_eq!(get_in(&path, "qr_size").unwrap(), Some(512));
+        assert_eq!(
+            get_in::<Vec<String>>(&path, "words").unwrap().unwrap(),
+            ["correct", "horse"]
+        );
+        // Wrong types are an error instead of a silent default
+        assert!(get_in::<String>(&path, "qr_size").is_err());
+
+        assert!(remove_in(&path, "qr_size").unwrap());
+        assert!(!remove_in(&path, "qr_size").unwrap());
+        assert_eq!(get_in::<u32>(&path, "qr_size").unwrap(), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+//! Command line client for scripting a running pointy instance, e.g.
+//! `pointyctl run generate_qrcode` or `pointyctl config get > config.json`.
+
+use std::{fs, io::Read, path::PathBuf, process::ExitCode};
+
+use serde_json::{json, Value};
+
+pub const USAGE: &str = "\
+Usage: pointyctl [--socket <path>] <command>
+
+Commands:
+  toggle                     Show the wheel, or select in it if it is shown
+  show                       Show the wheel
+  hide                       Hide the wheel without selecting
+  run <id>                   Run an installed extension
+  list                       List the installed extensions
+  install <id> <latest_url>  Install an extension from its release url
+  update                     Update all installed extensions
+  config get                 Print the config
+  config set <file>          Replace the config, `-` reads it from stdin
+  help                       Print this help";
+
+/// A command of the CLI, each calling one method of the running instance
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Toggle,
+    Show,
+    Hide,
+    Run { id: String },
+    List,
+    Install { id: String, latest_url: String },
+    Update,
+    GetConfig,
+    SetConfig { path: PathBuf },
+    Help,
+}
+
+impl Command {
+    /// Parses the arguments following the options
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["toggle"] => Ok(Command::Toggle),
+            ["show"] => Ok(Command::Show),
+            ["hide"] => Ok(Command::Hide),
+            ["run", id] => Ok(Command::Run { id: id.to_string() }),
+            ["list"] => Ok(Command::List),
+            ["install", id, latest_url] => Ok(Command::Install {
+                id: id.to_string(),
+                latest_url: latest_url.to_string(),
+            }),
+            ["update"] => Ok(Command::Update),
+            ["config", "get"] => Ok(Command::GetConfig),
+            ["config", "set", path] => Ok(Command::SetConfig { path: path.into() }),
+            ["help" | "--help" | "-h"] => Ok(Command::Help),
+            [] => Err("missing command".into()),
+            [command, ..] if is_command(command) => Err(format!("wrong arguments for '{command}'")),
+            [command, ..] => Err(format!("unknown command '{command}'")),
+        }
+    }
+
+    /// Returns the method and params to call, reading a config file if needed
+    pub fn request(&self) -> Result<(&'static str, Value), String> {
+        Ok(match self {
+            Command::Toggle => ("toggle", Value::Null),
+            Command::Show => ("show", Value::Null),
+            Command::Hide => ("hide", Value::Null),
+            Command::Run { id } => ("run_extension", json!({ "id": id })),
+            Command::List => ("list_extensions", Value::Null),
+            Command::Install { id, latest_url } => (
+                "install_extension",
+                json!({ "id": id, "latest_url": latest_url }),
+            ),
+            Command::Update => ("update_extensions", Value::Null),
+            Command::GetConfig => ("get_config", Value::Null),
+            Command::SetConfig { path } => {
+                let data = if path.as_os_str() == "-" {
+                    let mut data = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut data)
+                        .map_err(|e| e.to_string())?;
+                    data
+                } else {
+                    fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?
+                };
+                let config: Value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+                ("change_config", json!({ "config": config }))
+            }
+            Command::Help => return Err(USAGE.into()),
+        })
+    }
+}
+
+/// Whether `arg` starts a command, for telling them apart from other arguments of the app
+pub fn is_command(arg: &str) -> bool {
+    matches!(
+        arg,
+        "toggle" | "show" | "hide" | "run" | "list" | "install" | "update" | "config" | "help"
+    )
+}
+
+/// Runs the CLI with `args`, not including the program name
+pub fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let mut args: Vec<String> = args.into_iter().collect();
+
+    let mut socket = pointy_ipc::socket_path();
+    if args.first().is_some_and(|a| a == "--socket") {
+        let Some(path) = args.get(1) else {
+            eprintln!("error: missing path for '--socket'\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        };
+        socket = path.into();
+        args.drain(..2);
+    }
+
+    let command = match Command::parse(&args) {
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match call(&socket, &command) {
+        Ok(Value::Null) => ExitCode::SUCCESS,
+        Ok(value) => {
+            println!("{value:#}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(unix)]
+fn call(socket: &std::path::Path, command: &Command) -> Result<Value, String> {
+    let (method, params) = command.request()?;
+    let mut client = pointy_ipc::Client::connect(socket)
+        .map_err(|e| format!("failed to connect to pointy at {}: {e}", socket.display()))?;
+    client.call(method, params).map_err(|e| e.to_string())
+}
+
+#[cfg(not(unix))]
+fn call(_socket: &std::path::Path, _command: &Command) -> Result<Value, String> {
+    Err("controlling pointy is only supported on unix".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse(&["toggle"]), Ok(Command::Toggle));
+        assert_eq!(
+            parse(&["run", "generate_qrcode"]),
+            Ok(Command::Run {
+                id: "generate_qrcode".into()
+            })
+        );
+        assert_eq!(parse(&["config", "get"]), Ok(Command::GetConfig));
+        assert_eq!(
+            parse(&["config", "set", "-"]),
+            Ok(Command::SetConfig { path: "-".into() })
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_arguments() {
+        assert_eq!(parse(&[]), Err("missing command".into()));
+        assert_eq!(parse(&["run"]), Err("wrong arguments for 'run'".into()));
+        assert_eq!(
+            parse(&["pointy://install"]),
+            Err("unknown command 'pointy://install'".into())
+        );
+    }
+
+    #[test]
+    fn commands_map_to_methods() {
+        let install = Command::Install {
+            id: "math_equasion".into(),
+            latest_url: "https://example.com/latest".into(),
+        };
+        assert_eq!(
+            install.request(),
+            Ok((
+                "install_extension",
+                json!({ "id": "math_equasion", "latest_url": "https://example.com/latest" })
+            ))
+        );
+        assert_eq!(
+            Command::List.request(),
+            Ok(("list_extensions", Value::Null))
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    fmt, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+use serde_json::{json, Value};
+
+use crate::{token_path, Outcome, Request, Response, RpcError, AUTHENTICATE_METHOD};
+
+/// Connection to a running instance
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connects to the socket at `path` and authenticates with the token next to it
+    pub fn connect(path: &Path) -> Result<Self, ClientError> {
+        let writer = UnixStream::connect(path)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        let mut client = Self {
+            reader,
+            writer,
+            next_id: 1,
+        };
+
+        let token = fs::read_to_string(token_path(path))?;
+        client.call(AUTHENTICATE_METHOD, json!({ "token": token.trim() }))?;
+        Ok(client)
+    }
+
+    /// Calls `method` and waits for its result
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value, ClientError> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = Request::new(id, method, params);
+        writeln!(self.writer, "{}", serde_json::to_string(&request)?)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let response: Response = serde_json::from_str(&line)?;
+        if response.id != Some(id) {
+            return Err(ClientError::Rpc(RpcError::new(
+                RpcError::INTERNAL_ERROR,
+                format!("expected the response to {id}, got {:?}", response.id),
+            )));
+        }
+        match response.outcome {
+            Outcome::Result(value) => Ok(value),
+            Outcome::Error(e) => Err(ClientError::Rpc(e)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection failed, e.g. because pointy isn't running
+    Io(io::Error),
+    /// The response couldn't be read
+    Json(serde_json::Error),
+    /// The call failed
+    Rpc(RpcError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "connection error: {}", e),
+            ClientError::Json(e) => write!(f, "invalid response: {}", e),
+            ClientError::Rpc(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(err: serde_json::Error) -> Self {
+        ClientError::Json(err)
+    }
+}
@@ -0,0 +1,343 @@
+//! JSON-RPC 2.0 over a local socket, for driving a running pointy instance
+//! from scripts, its CLI or compositor keybindings.
+//!
+//! Requests and responses are sent as one JSON object per line.
+//! Every connection first calls [`AUTHENTICATE_METHOD`] with the token written next to the socket,
+//! so only processes able to read it, unlike sandboxed extensions, can control pointy.
+
+#[cfg(unix)]
+mod client;
+#[cfg(unix)]
+mod server;
+
+use std::{fmt, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(unix)]
+pub use client::{Client, ClientError};
+#[cfg(unix)]
+pub use server::{bind, serve, Listener};
+
+/// Version of the protocol, sent with every message
+pub const JSONRPC_VERSION: &str = "2.0";
+/// Overrides the socket path, e.g. for running a second instance in tests
+pub const SOCKET_ENV: &str = "POINTY_SOCKET";
+/// Has to be called first on every connection, with the token as `{"token": "..."}`
+pub const AUTHENTICATE_METHOD: &str = "authenticate";
+
+/// A method call, without an id it is a notification and isn't answered
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: impl Into<String>, params: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.into(),
+            id: Some(id),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// Answer to a [`Request`] with the same id, `None` if the request couldn't be read
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl Response {
+    pub fn new(id: Option<u64>, outcome: Outcome) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.into(),
+            id,
+            outcome,
+        }
+    }
+}
+
+/// Either the result or the error of a call
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Result(Value),
+    Error(RpcError),
+}
+
+impl From<Result<Value, RpcError>> for Outcome {
+    fn from(result: Result<Value, RpcError>) -> Self {
+        match result {
+            Ok(value) => Outcome::Result(value),
+            Err(e) => Outcome::Error(e),
+        }
+    }
+}
+
+/// Error object of a failed call
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    /// For application errors the serialized error of the backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// The request isn't valid JSON
+    pub const PARSE_ERROR: i64 = -32700;
+    /// The JSON isn't a valid request
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// The method was called but failed
+    pub const APPLICATION_ERROR: i64 = -32000;
+    /// The connection didn't authenticate first, it is closed afterwards
+    pub const UNAUTHORIZED: i64 = -32001;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(
+            Self::METHOD_NOT_FOUND,
+            format!("method '{method}' not found"),
+        )
+    }
+
+    pub fn invalid_params(e: impl fmt::Display) -> Self {
+        Self::new(Self::INVALID_PARAMS, format!("invalid params: {e}"))
+    }
+
+    /// Deserializes the params of a call, like `{"id": "math_equasion"}`
+    pub fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, Self> {
+        serde_json::from_value(params).map_err(Self::invalid_params)
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// Returns the socket path, preferring the per-user runtime directory
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join("pointy.sock")
+}
+
+/// Returns the path of the token belonging to the socket at `socket`
+pub fn token_path(socket: &std::path::Path) -> PathBuf {
+    socket.with_extension("token")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        fs,
+        io::{BufRead, BufReader, Write},
+        os::unix::{fs::PermissionsExt, net::UnixStream},
+        thread,
+    };
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// A socket path of this test process, removed along with its token when dropped
+    struct Socket(PathBuf);
+
+    impl Socket {
+        fn new(name: &str) -> Self {
+            let name = format!("pointy-ipc-{}-{name}.sock", std::process::id());
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl std::ops::Deref for Socket {
+        type Target = std::path::Path;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(token_path(&self.0));
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Starts a server with a handler like a backend, echoing `echo` and failing `fail`
+    fn start(name: &str) -> Socket {
+        let path = Socket::new(name);
+        let listener = bind(&path).unwrap();
+        thread::spawn(move || {
+            serve(listener, |method, params| match method {
+                "echo" => Ok(params),
+                "fail" => Err(RpcError {
+                    data: Some(json!({ "kind": "Wheel", "value": "nope" })),
+                    ..RpcError::new(RpcError::APPLICATION_ERROR, "wheel error: nope")
+                }),
+                _ => Err(RpcError::method_not_found(method)),
+            })
+        });
+        path
+    }
+
+    /// Connects without a [`Client`], authenticating like one
+    fn authenticated(path: &std::path::Path) -> UnixStream {
+        let stream = UnixStream::connect(path).unwrap();
+        let token = fs::read_to_string(token_path(path)).unwrap();
+        let request = Request::new(0, AUTHENTICATE_METHOD, json!({ "token": token }));
+        writeln!(&stream, "{}", serde_json::to_string(&request).unwrap()).unwrap();
+
+        let mut response = String::new();
+        BufReader::new(&stream).read_line(&mut response).unwrap();
+        let response: Response = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.outcome, Outcome::Result(Value::Null));
+        stream
+    }
+
+    #[test]
+    fn calls_return_results() {
+        let path = start("results");
+        let mut client = Client::connect(&path).unwrap();
+
+        assert_eq!(
+            client.call("echo", json!({ "id": "qr" })).unwrap(),
+            json!({ "id": "qr" })
+        );
+        assert_eq!(client.call("echo", Value::Null).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn errors_are_forwarded() {
+        let path = start("errors");
+        let mut client = Client::connect(&path).unwrap();
+
+        let Err(ClientError::Rpc(e)) = client.call("fail", Value::Null) else {
+            panic!("call should fail");
+        };
+        assert_eq!(e.code, RpcError::APPLICATION_ERROR);
+        assert_eq!(e.data, Some(json!({ "kind": "Wheel", "value": "nope" })));
+
+        let Err(ClientError::Rpc(e)) = client.call("unknown", Value::Null) else {
+            panic!("call should fail");
+        };
+        assert_eq!(e.code, RpcError::METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn unauthenticated_connections_are_closed() {
+        let path = start("unauthenticated");
+        for first in [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "echo"}"#,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": {"token": "guess"}}"#,
+        ] {
+            let stream = UnixStream::connect(&*path).unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            writeln!(writer, "{first}").unwrap();
+
+            let mut response = String::new();
+            reader.read_line(&mut response).unwrap();
+            let response: Response = serde_json::from_str(&response).unwrap();
+            assert!(
+                matches!(response.outcome, Outcome::Error(e) if e.code == RpcError::UNAUTHORIZED)
+            );
+            // Nothing is answered anymore
+            let _ = writeln!(writer, r#"{{"jsonrpc": "2.0", "id": 2, "method": "echo"}}"#);
+            assert!(!matches!(reader.read_line(&mut String::new()), Ok(1..)));
+        }
+    }
+
+    #[test]
+    fn malformed_requests_are_answered() {
+        let path = start("malformed");
+        let stream = authenticated(&path);
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut answer = |line: &str| {
+            writeln!(writer, "{line}").unwrap();
+            let mut response = String::new();
+            reader.read_line(&mut response).unwrap();
+            serde_json::from_str::<Response>(&response).unwrap()
+        };
+
+        let response = answer("{ not json");
+        assert_eq!(response.id, None);
+        assert!(matches!(response.outcome, Outcome::Error(e) if e.code == RpcError::PARSE_ERROR));
+
+        let response = answer(r#"{"jsonrpc": "1.0", "id": 3, "method": "echo"}"#);
+        assert_eq!(response.id, Some(3));
+        assert!(
+            matches!(response.outcome, Outcome::Error(e) if e.code == RpcError::INVALID_REQUEST)
+        );
+    }
+
+    #[test]
+    fn notifications_are_not_answered() {
+        let path = start("notifications");
+        let stream = authenticated(&path);
+        let mut writer = stream.try_clone().unwrap();
+        writeln!(writer, r#"{{"jsonrpc": "2.0", "method": "echo"}}"#).unwrap();
+        writeln!(writer, r#"{{"jsonrpc": "2.0", "id": 1, "method": "echo"}}"#).unwrap();
+
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Response>(&response).unwrap().id,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn socket_is_private() {
+        let path = start("private");
+        for path in [token_path(&path), path.to_path_buf()] {
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn stale_sockets_are_replaced() {
+        let path = Socket::new("stale");
+        drop(bind(&path).unwrap());
+        assert!(path.exists());
+
+        // The first listener is gone, so binding again succeeds
+        let _listener = bind(&path).unwrap();
+        // But not while another instance is listening
+        assert!(bind(&path).is_err());
+    }
+}
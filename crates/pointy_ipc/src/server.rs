@@ -0,0 +1,222 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    os::{
+        fd::AsRawFd,
+        unix::{
+            fs::{OpenOptionsExt, PermissionsExt},
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::Path,
+    sync::Arc,
+    thread,
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{
+    token_path, Outcome, Request, Response, RpcError, AUTHENTICATE_METHOD, JSONRPC_VERSION,
+};
+
+/// A bound socket along with the token its connections have to authenticate with
+pub struct Listener {
+    socket: UnixListener,
+    token: String,
+}
+
+/// Binds the socket at `path`, readable and writable only by the current user,
+/// and writes a new token to [`token_path`], only readable by the current user as well.
+///
+/// A socket left behind by a crashed instance is replaced,
+/// one of a running instance makes this fail with [`io::ErrorKind::AddrInUse`].
+pub fn bind(path: &Path) -> io::Result<Listener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is used by a running instance", path.display()),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    let socket = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    let token = new_token()?;
+    let token_path = token_path(path);
+    match fs::remove_file(&token_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    // Created private, so it is never readable by others
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&token_path)?
+        .write_all(token.as_bytes())?;
+
+    Ok(Listener { socket, token })
+}
+
+/// Answers the requests of every connection with `handler`, until the listener fails.
+///
+/// Each connection gets its own thread, so long running calls don't block others.
+/// Connections of other users are closed right away, those not authenticating after their first request.
+pub fn serve<F>(listener: Listener, handler: F)
+where
+    F: Fn(&str, Value) -> Result<Value, RpcError> + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    let token = Arc::new(listener.token);
+    for stream in listener.socket.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(error = %e, "failed to accept ipc connection");
+                continue;
+            }
+        };
+
+        match peer_uid(&stream) {
+            Ok(uid) if uid == current_uid() => {}
+            Ok(uid) => {
+                warn!(uid, "rejected ipc connection of another user");
+                continue;
+            }
+            Err(e) => {
+                warn!(error = %e, "failed to check ipc peer");
+                continue;
+            }
+        }
+
+        let (handler, token) = (handler.clone(), token.clone());
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &token, &*handler) {
+                warn!(error = %e, "failed to handle ipc connection");
+            }
+        });
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthenticateParams {
+    token: String,
+}
+
+fn handle_connection<F>(stream: UnixStream, token: &str, handler: &F) -> io::Result<()>
+where
+    F: Fn(&str, Value) -> Result<Value, RpcError>,
+{
+    let mut writer = stream.try_clone()?;
+    let mut authenticated = false;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if !authenticated {
+            let request = serde_json::from_str::<Request>(&line).ok();
+            authenticated = request.as_ref().is_some_and(|request| {
+                request.method == AUTHENTICATE_METHOD
+                    && serde_json::from_value::<AuthenticateParams>(request.params.clone())
+                        .is_ok_and(|params| params.token == token)
+            });
+            let outcome = if authenticated {
+                Outcome::Result(Value::Null)
+            } else {
+                Outcome::Error(RpcError::new(
+                    RpcError::UNAUTHORIZED,
+                    "the connection has to authenticate first",
+                ))
+            };
+            let id = request.and_then(|request| request.id);
+            writeln!(
+                writer,
+                "{}",
+                serde_json::to_string(&Response::new(id, outcome))?
+            )?;
+            if !authenticated {
+                return Ok(());
+            }
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Err(e) => Response::new(
+                None,
+                Outcome::Error(RpcError::new(RpcError::PARSE_ERROR, e.to_string())),
+            ),
+            Ok(request) if request.jsonrpc != JSONRPC_VERSION => Response::new(
+                request.id,
+                Outcome::Error(RpcError::new(
+                    RpcError::INVALID_REQUEST,
+                    format!("unsupported version '{}'", request.jsonrpc),
+                )),
+            ),
+            Ok(request) => {
+                let outcome = handler(&request.method, request.params).into();
+                match request.id {
+                    Some(id) => Response::new(Some(id), outcome),
+                    // Notifications aren't answered
+                    None => continue,
+                }
+            }
+        };
+
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    }
+    Ok(())
+}
+
+/// Random hex characters, read from the kernel
+fn new_token() -> io::Result<String> {
+    let mut bytes = [0u8; 32];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: always succeeds
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` and `len` are valid for writes and `len` is its size
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: `uid` and `gid` are valid for writes
+    let ret = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
@@ -0,0 +1,89 @@
+//! Calls of extensions into the host needing a permission, see [`HostCall`].
+//!
+//! They are performed by the runner, only once checked against the permissions of the run.
+
+use std::borrow::Cow;
+
+use pointy_api::{
+    arboard::{Clipboard, ImageData},
+    device_query::{DeviceQuery, DeviceState},
+    host::{image_from_bytes, image_to_bytes},
+    image::RgbaImage,
+    permissions::check,
+    HostCall, Permission,
+};
+use xcap::Monitor;
+
+/// Performs `call` with `input` if `permissions` allow it, encoded as described by [`HostCall`]
+pub fn perform(
+    permissions: &[Permission],
+    call: HostCall,
+    input: &[u8],
+) -> Result<Vec<u8>, String> {
+    check(permissions, &needed(call))?;
+    match call {
+        HostCall::ClipboardGetText => clipboard()?
+            .get_text()
+            .map(String::into_bytes)
+            .map_err(|e| e.to_string()),
+        HostCall::ClipboardGetImage => {
+            let image = clipboard()?.get_image().map_err(|e| e.to_string())?;
+            let image = RgbaImage::from_raw(
+                image.width as u32,
+                image.height as u32,
+                image.bytes.into_owned(),
+            )
+            .ok_or("the clipboard image is smaller than its size")?;
+            Ok(image_to_bytes(&image))
+        }
+        HostCall::ClipboardWriteText => {
+            let text = String::from_utf8(input.to_vec()).map_err(|e| e.to_string())?;
+            clipboard()?.set_text(text).map_err(|e| e.to_string())?;
+            Ok(Vec::new())
+        }
+        HostCall::ClipboardWriteHtml => {
+            let (html, alt_text): (String, Option<String>) =
+                serde_json::from_slice(input).map_err(|e| e.to_string())?;
+            clipboard()?
+                .set_html(html, alt_text)
+                .map_err(|e| e.to_string())?;
+            Ok(Vec::new())
+        }
+        HostCall::ClipboardWriteImage => {
+            let image = image_from_bytes(input.to_vec())?;
+            let image = ImageData {
+                width: image.width() as usize,
+                height: image.height() as usize,
+                bytes: Cow::Borrowed(image.as_raw()),
+            };
+            clipboard()?.set_image(image).map_err(|e| e.to_string())?;
+            Ok(Vec::new())
+        }
+        HostCall::ClipboardClear => {
+            clipboard()?.clear().map_err(|e| e.to_string())?;
+            Ok(Vec::new())
+        }
+        HostCall::CaptureScreen => {
+            let (x, y) = DeviceState::new().get_mouse().coords;
+            let monitor = Monitor::from_point(x, y).map_err(|e| e.to_string())?;
+            let image = monitor.capture_image().map_err(|e| e.to_string())?;
+            Ok(image_to_bytes(&image))
+        }
+    }
+}
+
+/// The permission `call` needs
+fn needed(call: HostCall) -> Permission {
+    match call {
+        HostCall::ClipboardGetText | HostCall::ClipboardGetImage => Permission::ClipboardRead,
+        HostCall::ClipboardWriteText
+        | HostCall::ClipboardWriteHtml
+        | HostCall::ClipboardWriteImage
+        | HostCall::ClipboardClear => Permission::ClipboardWrite,
+        HostCall::CaptureScreen => Permission::ScreenCapture,
+    }
+}
+
+fn clipboard() -> Result<Clipboard, String> {
+    Clipboard::new().map_err(|e| e.to_string())
+}
@@ -0,0 +1,98 @@
+//! The runner process, running a single extension.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::{fd::FromRawFd, unix::net::UnixStream},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use pointy_api::Permission;
+
+#[cfg(target_os = "linux")]
+use crate::sandbox::{self, Enforced};
+use crate::{
+    Extension, ExtensionKind, RunnerCommand, RunnerMessage, CONTROL_FD, PARTIAL_SANDBOX_WARNING,
+    RUNNER_ARG,
+};
+
+/// Runs the extension given by the arguments, without the program name.
+/// Only meant to be started by the host, see [`run`](crate::run).
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let args: Vec<String> = args.collect();
+    let [arg, kind, path, data_dir, permissions, memory_limit @ ..] = args.as_slice() else {
+        eprintln!("usage: {RUNNER_ARG} <kind> <extension> <data dir> <permissions> [memory limit]");
+        return ExitCode::FAILURE;
+    };
+    // SAFETY: only checks whether the descriptor is open
+    if arg != RUNNER_ARG || unsafe { libc::fcntl(CONTROL_FD, libc::F_GETFD) } == -1 {
+        eprintln!("error: the runner has to be started by pointy");
+        return ExitCode::FAILURE;
+    }
+
+    // SAFETY: passed by the host and not used otherwise
+    let control = unsafe { UnixStream::from_raw_fd(CONTROL_FD) };
+    // Processes started by the extension shouldn't keep the host waiting
+    unsafe { libc::fcntl(CONTROL_FD, libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    // Unreadable ones grant nothing
+    let permissions: Vec<Permission> = serde_json::from_str(permissions).unwrap_or_default();
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    if let Ok(reader) = control.try_clone() {
+        let cancelled = cancelled.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                if let Ok(RunnerCommand::Cancel) = serde_json::from_str(&line) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    let control = Mutex::new(control);
+    let send = |message: RunnerMessage| {
+        if let (Ok(mut control), Ok(line)) = (control.lock(), serde_json::to_string(&message)) {
+            let _ = writeln!(control, "{line}");
+        }
+    };
+
+    let (path, data_dir) = (PathBuf::from(path), PathBuf::from(data_dir));
+    let memory_limit = memory_limit.first().and_then(|bytes| bytes.parse().ok());
+    // Sandboxed before loading, as the constructors of a library run as soon as it is loaded
+    let result = kind.parse::<ExtensionKind>().and_then(|kind| {
+        #[cfg(target_os = "linux")]
+        lockdown(&path, &data_dir, &permissions, &send)?;
+        Extension::load(kind, &path)?
+            .with_memory_limit(memory_limit)
+            .call(&data_dir, &permissions, &cancelled, &send)
+    });
+    send(RunnerMessage::Finished {
+        error: result.err(),
+    });
+    ExitCode::SUCCESS
+}
+
+/// Sandboxes the runner before the extension gets to run, warning if the kernel can't fully
+/// enforce it
+#[cfg(target_os = "linux")]
+fn lockdown(
+    path: &Path,
+    data_dir: &Path,
+    permissions: &[Permission],
+    send: &dyn Fn(RunnerMessage),
+) -> Result<(), String> {
+    let extension_dir = path.parent().unwrap_or(path);
+    if sandbox::apply(extension_dir, data_dir, permissions)? == Enforced::Partially {
+        send(RunnerMessage::Log {
+            level: pointy_api::Level::Warn as u32,
+            message: PARTIAL_SANDBOX_WARNING.to_string(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,349 @@
+//! Runs extensions in a separate runner process, so their output can be captured
+//! and a stuck one can be stopped for good.
+//!
+//! The host starts its own executable with [`RUNNER_ARG`], the kind and file of the extension,
+//! its data dir and its granted permissions.
+//! Calls of the extension into the host are sent back as [`RunnerMessage`]s,
+//! one JSON object per line over a socket passed as [`CONTROL_FD`].
+//! On Linux the runner is sandboxed to the granted permissions before the extension runs.
+//!
+//! Extensions are either native libraries or WebAssembly components, see [`ExtensionKind`].
+
+mod calls;
+#[cfg(unix)]
+pub mod child;
+#[cfg(unix)]
+mod parent;
+#[cfg(target_os = "linux")]
+mod sandbox;
+mod wasm;
+
+use std::{
+    ffi::{c_void, CStr, CString},
+    fmt,
+    os::raw::c_char,
+    path::Path,
+    slice,
+    str::FromStr,
+    sync::atomic::AtomicBool,
+    time::Duration,
+};
+
+use libloading::{Library, Symbol};
+use pointy_api::{
+    host::{HostContext, HOST_CONTEXT_VERSION},
+    HostCall, Permission,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(unix)]
+pub use parent::{run, RunError, RunOptions, RunOutcome};
+
+/// First argument of a runner process, followed by the [`ExtensionKind`], the paths of the
+/// extension and its data dir, the granted permissions as JSON
+/// and for WebAssembly extensions optionally their memory limit in bytes
+pub const RUNNER_ARG: &str = "--run-extension";
+/// File descriptor of the socket to the host in a runner process
+pub const CONTROL_FD: i32 = 3;
+/// Logged by the runner if the kernel can't fully sandbox the filesystem
+pub const PARTIAL_SANDBOX_WARNING: &str =
+    "the filesystem isn't fully sandboxed, Landlock is unsupported by this kernel";
+
+/// Sent by the runner to the host while an extension runs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", content = "value")]
+pub enum RunnerMessage {
+    /// See [`pointy_api::report_progress`]
+    Progress { fraction: f32, message: String },
+    /// See [`pointy_api::notify`]
+    Notify { title: String, body: String },
+    /// See [`pointy_api::log`], `level` being a [`pointy_api::Level`]
+    Log { level: u32, message: String },
+    /// The extension was stopped for exceeding `limit`, sent before it finishes
+    LimitExceeded { limit: Limit },
+    /// The extension returned, with its error if it failed
+    Finished { error: Option<String> },
+}
+
+/// Sent by the host to the runner
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", content = "value")]
+pub enum RunnerCommand {
+    /// Makes [`pointy_api::is_cancelled`] return true
+    Cancel,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// A line the extension wrote to stdout or stderr
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputLine {
+    /// Milliseconds since the start of the run
+    pub at_ms: u64,
+    pub stream: Stream,
+    pub text: String,
+}
+
+/// Resource limits of the runner process, only enforced on Linux apart from WebAssembly memory
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub cpu_time: Option<Duration>,
+    /// In bytes, of WebAssembly extensions only their linear memory
+    pub memory: Option<u64>,
+}
+
+/// A resource limit of [`Limits`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    CpuTime,
+    Memory,
+}
+
+/// How an extension is built, declared in its manifest
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtensionKind {
+    /// A dynamic library built for each platform, running with the privileges of its runner
+    #[default]
+    Native,
+    /// A WebAssembly component of the `extension` world in `wit/extension.wit`,
+    /// only able to reach the host through its imports
+    Wasm,
+}
+
+impl ExtensionKind {
+    /// Name of the file to run within the directory of the extension
+    pub fn filename(self) -> &'static str {
+        match self {
+            #[cfg(target_os = "windows")]
+            ExtensionKind::Native => "lib.dll",
+            #[cfg(target_os = "macos")]
+            ExtensionKind::Native => "lib.dylib",
+            #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+            ExtensionKind::Native => "lib.so",
+            ExtensionKind::Wasm => "extension.wasm",
+        }
+    }
+}
+
+impl fmt::Display for ExtensionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtensionKind::Native => write!(f, "native"),
+            ExtensionKind::Wasm => write!(f, "wasm"),
+        }
+    }
+}
+
+impl FromStr for ExtensionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(ExtensionKind::Native),
+            "wasm" => Ok(ExtensionKind::Wasm),
+            _ => Err(format!("unknown extension kind '{s}'")),
+        }
+    }
+}
+
+/// Receives the calls of the extension into the host
+type Sink<'a> = &'a dyn Fn(RunnerMessage);
+
+/// What the callbacks of a native run get as [`HostContext::data`]
+struct Run<'a> {
+    sink: Sink<'a>,
+    permissions: &'a [Permission],
+}
+
+/// A loaded extension, ready to be called
+pub enum Extension {
+    /// Loaded along with the libraries it links to
+    Native(Library),
+    /// Compiled, but only instantiated once called
+    Wasm(wasm::Component),
+}
+
+impl Extension {
+    pub fn load(kind: ExtensionKind, path: &Path) -> Result<Self, String> {
+        match kind {
+            ExtensionKind::Native => unsafe { Library::new(path) }
+                .map(Extension::Native)
+                .map_err(|e| e.to_string()),
+            ExtensionKind::Wasm => wasm::Component::load(path).map(Extension::Wasm),
+        }
+    }
+
+    /// Limits the linear memory of a WebAssembly extension to `bytes`.
+    /// Native ones can only be limited through their runner process.
+    pub fn with_memory_limit(self, bytes: Option<u64>) -> Self {
+        match self {
+            Extension::Wasm(component) => Extension::Wasm(component.with_memory_limit(bytes)),
+            native => native,
+        }
+    }
+
+    /// Calls the extension, `data_dir` and `permissions` are passed on to it,
+    /// its calls into the host to `sink`.
+    ///
+    /// A WebAssembly extension is interrupted shortly after being cancelled,
+    /// a native one has to return by itself.
+    pub fn call(
+        &self,
+        data_dir: &Path,
+        permissions: &[Permission],
+        cancelled: &AtomicBool,
+        sink: &(dyn Fn(RunnerMessage) + Sync),
+    ) -> Result<(), String> {
+        match self {
+            Extension::Native(library) => {
+                call_library(library, data_dir, permissions, cancelled, sink)
+            }
+            Extension::Wasm(component) => component.call(data_dir, permissions, cancelled, sink),
+        }
+    }
+}
+
+/// Calls the entry point of `library`, preferring the one taking a context.
+///
+/// The function is assumed to be one of:
+///
+///   pub extern "C" fn run_with_context(ctx: *const HostContext) -> *mut c_char
+///   pub extern "C" fn run() -> *mut c_char
+///
+fn call_library(
+    library: &Library,
+    data_dir: &Path,
+    permissions: &[Permission],
+    cancelled: &AtomicBool,
+    sink: &dyn Fn(RunnerMessage),
+) -> Result<(), String> {
+    let run = Run { sink, permissions };
+    let data_dir =
+        CString::new(data_dir.to_string_lossy().as_bytes()).map_err(|e| e.to_string())?;
+    let permissions = serde_json::to_string(permissions).map_err(|e| e.to_string())?;
+    let permissions = CString::new(permissions).map_err(|e| e.to_string())?;
+    let ctx = HostContext {
+        version: HOST_CONTEXT_VERSION,
+        cancelled,
+        data: &run as *const Run as *mut c_void,
+        report_progress,
+        notify,
+        log,
+        call,
+        data_dir: data_dir.as_ptr(),
+        permissions: permissions.as_ptr(),
+    };
+
+    unsafe {
+        type RunWithContext = unsafe extern "C" fn(*const HostContext) -> *mut c_char;
+        type Run = unsafe extern "C" fn() -> *mut c_char;
+
+        let raw_ptr = match library.get::<RunWithContext>(b"run_with_context\0") {
+            Ok(func) => func(&ctx),
+            // Extensions built against an older `pointy_api`
+            Err(_) => {
+                let func: Symbol<Run> = library.get(b"run\0").map_err(|e| e.to_string())?;
+                func()
+            }
+        };
+        if raw_ptr.is_null() {
+            return Err("Extension returned a null pointer".to_string());
+        }
+
+        let result_str = CString::from_raw(raw_ptr)
+            .into_string()
+            .map_err(|e| e.to_string())?;
+
+        // As with the helper in `pointy_api`, if the string is empty no error occurred
+        if result_str.is_empty() {
+            Ok(())
+        } else {
+            Err(result_str)
+        }
+    }
+}
+
+/// Loads the extension at `path` and calls it, see [`Extension::call`]
+pub fn call_extension(
+    kind: ExtensionKind,
+    path: &Path,
+    data_dir: &Path,
+    permissions: &[Permission],
+    cancelled: &AtomicBool,
+    sink: &(dyn Fn(RunnerMessage) + Sync),
+) -> Result<(), String> {
+    Extension::load(kind, path)?.call(data_dir, permissions, cancelled, sink)
+}
+
+/// Passes `message` to the [`Sink`] of the run.
+///
+/// # Safety
+/// `data` has to be the [`HostContext::data`] of a run which didn't return yet.
+unsafe fn send(data: *mut c_void, message: RunnerMessage) {
+    ((*(data as *const Run)).sink)(message)
+}
+
+/// # Safety
+/// `text` has to be a valid C string, as guaranteed by `pointy_api`.
+unsafe fn read_string(text: *const c_char) -> String {
+    CStr::from_ptr(text).to_string_lossy().into_owned()
+}
+
+extern "C" fn report_progress(data: *mut c_void, fraction: f32, message: *const c_char) {
+    let fraction = if fraction.is_nan() { 0. } else { fraction };
+    // SAFETY: only called by the extension during its run
+    unsafe {
+        let message = read_string(message);
+        send(data, RunnerMessage::Progress { fraction, message })
+    }
+}
+
+extern "C" fn notify(data: *mut c_void, title: *const c_char, body: *const c_char) {
+    // SAFETY: only called by the extension during its run
+    unsafe {
+        let (title, body) = (read_string(title), read_string(body));
+        send(data, RunnerMessage::Notify { title, body })
+    }
+}
+
+extern "C" fn log(data: *mut c_void, level: u32, message: *const c_char) {
+    // SAFETY: only called by the extension during its run
+    unsafe {
+        let message = read_string(message);
+        send(data, RunnerMessage::Log { level, message })
+    }
+}
+
+extern "C" fn call(
+    data: *mut c_void,
+    call: u32,
+    input: *const u8,
+    input_len: usize,
+    out: *mut c_void,
+    write: extern "C" fn(out: *mut c_void, bytes: *const u8, len: usize),
+) -> bool {
+    // SAFETY: only called by the extension during its run, with `input` as `pointy_api` passes it
+    let (run, input) = unsafe {
+        let input = if input.is_null() {
+            &[]
+        } else {
+            slice::from_raw_parts(input, input_len)
+        };
+        (&*(data as *const Run), input)
+    };
+    let result = HostCall::from_u32(call)
+        .ok_or_else(|| format!("unknown host call {call}"))
+        .and_then(|call| calls::perform(run.permissions, call, input));
+    let (ok, bytes) = match result {
+        Ok(output) => (true, output),
+        Err(e) => (false, e.into_bytes()),
+    };
+    write(out, bytes.as_ptr(), bytes.len());
+    ok
+}
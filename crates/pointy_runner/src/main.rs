@@ -0,0 +1,12 @@
+use std::process::ExitCode;
+
+#[cfg(unix)]
+fn main() -> ExitCode {
+    pointy_runner::child::run(std::env::args().skip(1))
+}
+
+#[cfg(not(unix))]
+fn main() -> ExitCode {
+    eprintln!("error: runner processes are only supported on unix");
+    ExitCode::FAILURE
+}
@@ -0,0 +1,307 @@
+//! Starting and supervising runner processes in the host.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::{
+            net::UnixStream,
+            process::{CommandExt, ExitStatusExt},
+        },
+    },
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use pointy_api::Permission;
+
+use crate::{
+    ExtensionKind, Limit, Limits, OutputLine, RunnerCommand, RunnerMessage, Stream, CONTROL_FD,
+    RUNNER_ARG,
+};
+
+/// How often cancelling and the timeout are checked
+const TICK: Duration = Duration::from_millis(50);
+/// Time the extension gets to return once asked to stop, before it is killed
+const GRACE_PERIOD: Duration = Duration::from_secs(2);
+/// Lines of output kept per run, the rest is dropped
+const MAX_OUTPUT_LINES: usize = 1000;
+
+pub struct RunOptions<'a> {
+    /// Executable handling [`RUNNER_ARG`], usually the host itself
+    pub program: &'a Path,
+    pub kind: ExtensionKind,
+    /// The library or component, see [`ExtensionKind::filename`]
+    pub path: &'a Path,
+    /// Passed on to [`pointy_api::data_dir`], has to exist
+    pub data_dir: &'a Path,
+    /// Granted to the extension, see [`pointy_api::permissions`]
+    pub permissions: &'a [Permission],
+    pub timeout: Duration,
+    pub limits: Limits,
+    /// Set once the run should stop
+    pub cancelled: &'a AtomicBool,
+}
+
+/// How a run went and what it wrote to stdout and stderr
+#[derive(Clone, Debug)]
+pub struct RunOutcome {
+    pub result: Result<(), RunError>,
+    pub output: Vec<OutputLine>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunError {
+    /// The extension returned an error
+    Failed(String),
+    Cancelled,
+    TimedOut,
+    /// The runner exited without the extension returning, e.g. because it panicked across FFI
+    Crashed(String),
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Failed(e) => write!(f, "{e}"),
+            RunError::Cancelled => write!(f, "cancelled"),
+            RunError::TimedOut => write!(f, "timed out"),
+            RunError::Crashed(status) => write!(f, "runner crashed: {status}"),
+            RunError::LimitExceeded(Limit::CpuTime) => write!(f, "exceeded its CPU time limit"),
+            RunError::LimitExceeded(Limit::Memory) => write!(f, "exceeded its memory limit"),
+        }
+    }
+}
+
+/// Runs the library in a new runner process and waits for it.
+///
+/// Calls of the extension into the host are passed to `on_message`.
+/// Once cancelled or timed out the extension is asked to stop and killed after a grace period.
+pub fn run(
+    options: &RunOptions,
+    mut on_message: impl FnMut(RunnerMessage),
+) -> io::Result<RunOutcome> {
+    let start = Instant::now();
+    let (control, runner_control) = UnixStream::pair()?;
+    let fd = runner_control.as_raw_fd();
+
+    let mut command = Command::new(options.program);
+    command
+        .arg(RUNNER_ARG)
+        .arg(options.kind.to_string())
+        .arg(options.path)
+        .arg(options.data_dir)
+        .arg(serde_json::to_string(options.permissions)?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Processes started by the extension are killed along with it
+        .process_group(0);
+    // WebAssembly is limited by the runtime instead, as the runner needs memory beyond it
+    let wasm = options.kind == ExtensionKind::Wasm;
+    if let Some(memory) = options.limits.memory.filter(|_| wasm) {
+        command.arg(memory.to_string());
+    }
+    #[cfg(target_os = "linux")]
+    let limits = Limits {
+        memory: options.limits.memory.filter(|_| !wasm),
+        ..options.limits
+    };
+    // SAFETY: only calls async-signal-safe functions
+    unsafe {
+        command.pre_exec(move || {
+            pass_control(fd)?;
+            #[cfg(target_os = "linux")]
+            set_limits(&limits)?;
+            Ok(())
+        })
+    };
+    let mut child = command.spawn()?;
+    drop(runner_control);
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let captures = [
+        capture(child.stdout.take(), Stream::Stdout, start, output.clone()),
+        capture(child.stderr.take(), Stream::Stderr, start, output.clone()),
+    ];
+
+    let (tx, rx) = mpsc::channel();
+    let reader = BufReader::new(control.try_clone()?);
+    thread::spawn(move || {
+        for line in reader.lines().map_while(Result::ok) {
+            // Anything else written to the socket is ignored
+            if let Ok(message) = serde_json::from_str::<RunnerMessage>(&line) {
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let deadline = start + options.timeout;
+    let mut finished = None;
+    let mut limit_exceeded = None;
+    let mut stopped: Option<(RunError, Instant)> = None;
+    let mut killed = false;
+    loop {
+        match rx.recv_timeout(TICK) {
+            Ok(RunnerMessage::Finished { error }) => finished = Some(error),
+            Ok(RunnerMessage::LimitExceeded { limit }) => limit_exceeded = Some(limit),
+            Ok(message) => on_message(message),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                // Something started by the extension may still hold the socket
+                if killed && matches!(child.try_wait(), Ok(Some(_))) {
+                    break;
+                }
+            }
+            // The runner exited
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        match &stopped {
+            _ if finished.is_some() => {}
+            None => {
+                let reason = if options.cancelled.load(Ordering::Relaxed) {
+                    Some(RunError::Cancelled)
+                } else if Instant::now() >= deadline {
+                    Some(RunError::TimedOut)
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    let _ = send(&control, &RunnerCommand::Cancel);
+                    stopped = Some((reason, Instant::now() + GRACE_PERIOD));
+                }
+            }
+            Some((_, kill_at)) if !killed && Instant::now() >= *kill_at => {
+                // SAFETY: the runner leads its own process group
+                unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+                killed = true;
+            }
+            Some(_) => {}
+        }
+    }
+
+    let status = child.wait()?;
+    // Output may still be buffered in the pipes, but nothing escaping the kill is waited for
+    let drained = Instant::now() + GRACE_PERIOD;
+    while captures.iter().flatten().any(|c| !c.is_finished()) && Instant::now() < drained {
+        thread::sleep(TICK);
+    }
+    let output = std::mem::take(&mut *output.lock().unwrap());
+
+    // Returning early once asked to stop still counts as stopped
+    let result = match (stopped, limit_exceeded, finished) {
+        (Some((reason, _)), _, _) => Err(reason),
+        (None, Some(limit), _) => Err(RunError::LimitExceeded(limit)),
+        (None, None, Some(None)) => Ok(()),
+        (None, None, Some(Some(e))) => Err(RunError::Failed(e)),
+        (None, None, None) => Err(exceeded(&status, &output).map_or_else(
+            || RunError::Crashed(status.to_string()),
+            RunError::LimitExceeded,
+        )),
+    };
+    Ok(RunOutcome { result, output })
+}
+
+/// Moves the control socket to [`CONTROL_FD`] in the runner, called between fork and exec
+fn pass_control(fd: RawFd) -> io::Result<()> {
+    // SAFETY: both are plain syscalls on descriptors owned by the new process
+    let result = unsafe {
+        if fd == CONTROL_FD {
+            // `dup2` wouldn't clear close-on-exec if both are the same
+            libc::fcntl(fd, libc::F_SETFD, 0)
+        } else {
+            libc::dup2(fd, CONTROL_FD)
+        }
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Limits the resources of the runner, called between fork and exec
+#[cfg(target_os = "linux")]
+fn set_limits(limits: &Limits) -> io::Result<()> {
+    let set = |resource, soft, hard| {
+        let limit = libc::rlimit {
+            rlim_cur: soft,
+            rlim_max: hard,
+        };
+        // SAFETY: a plain syscall
+        if unsafe { libc::setrlimit(resource, &limit) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+
+    if let Some(cpu_time) = limits.cpu_time {
+        // Exceeding the soft limit sends SIGXCPU, the hard one SIGKILL if it is ignored
+        let seconds = cpu_time.as_secs().max(1);
+        set(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+    }
+    if let Some(memory) = limits.memory {
+        // Unlike the address space, only counts memory actually allocated
+        set(libc::RLIMIT_DATA, memory, memory)?;
+    }
+    Ok(())
+}
+
+/// The limit which made the runner exit with `status`, if any
+fn exceeded(status: &ExitStatus, output: &[OutputLine]) -> Option<Limit> {
+    match status.signal()? {
+        libc::SIGXCPU => Some(Limit::CpuTime),
+        // Rust aborts once an allocation fails, after saying so
+        libc::SIGABRT
+            if output.iter().any(|line| {
+                line.stream == Stream::Stderr && line.text.starts_with("memory allocation of")
+            }) =>
+        {
+            Some(Limit::Memory)
+        }
+        _ => None,
+    }
+}
+
+fn send(mut control: &UnixStream, command: &RunnerCommand) -> io::Result<()> {
+    let line = serde_json::to_string(command)?;
+    writeln!(control, "{line}")
+}
+
+/// Collects the lines written to `pipe` until it is closed
+fn capture(
+    pipe: Option<impl Read + Send + 'static>,
+    stream: Stream,
+    start: Instant,
+    output: Arc<Mutex<Vec<OutputLine>>>,
+) -> Option<JoinHandle<()>> {
+    let mut reader = BufReader::new(pipe?);
+    Some(thread::spawn(move || {
+        let mut line = Vec::new();
+        while let Ok(1..) = reader.read_until(b'\n', &mut line) {
+            let text = String::from_utf8_lossy(&line);
+            let line_out = OutputLine {
+                at_ms: start.elapsed().as_millis() as u64,
+                stream,
+                text: text.trim_end_matches(['\n', '\r']).to_string(),
+            };
+            line.clear();
+
+            // Keeps reading past the limit, so the runner doesn't block on a full pipe
+            if let Ok(mut output) = output.lock() {
+                if output.len() < MAX_OUTPUT_LINES {
+                    output.push(line_out);
+                }
+            }
+        }
+    }))
+}
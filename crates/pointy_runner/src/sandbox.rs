@@ -0,0 +1,116 @@
+//! Confines a runner process to the permissions of its extension.
+//!
+//! Landlock limits the filesystem to the extension's own directory, its data dir and
+//! granted paths, next to read access to what libraries commonly need. Seccomp denies
+//! opening sockets other than unix ones, as used for the clipboard, unless networking is granted.
+//!
+//! This isn't full confinement: Landlock doesn't cover connecting to unix sockets, so services
+//! listening on them, like the session D-Bus or the X server, stay reachable.
+//! pointy's own control socket only accepts clients reading its token, which is outside the sandbox.
+
+use std::{collections::BTreeMap, env, path::Path};
+
+use landlock::{
+    path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+    ABI,
+};
+use pointy_api::Permission;
+use seccompiler::{
+    BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+    SeccompRule,
+};
+
+/// Newest Landlock version used, older kernels enforce what they support
+const LANDLOCK_ABI: ABI = ABI::V5;
+/// Readable by every extension, e.g. for shared libraries, fonts and devices
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/lib", "/lib64", "/etc", "/proc", "/sys", "/dev", "/nix",
+];
+/// Writable by every extension
+const SYSTEM_FILES: &[&str] = &["/dev/null", "/dev/zero", "/dev/full"];
+
+/// How far the kernel enforced the sandbox
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Enforced {
+    Fully,
+    /// The filesystem isn't or only partially restricted, e.g. without Landlock
+    Partially,
+}
+
+/// Restricts the calling thread, and threads it starts, to `permissions`.
+/// The seccomp filter is applied to all threads of the process.
+pub fn apply(
+    extension_dir: &Path,
+    data_dir: &Path,
+    permissions: &[Permission],
+) -> Result<Enforced, String> {
+    let enforced = restrict_filesystem(extension_dir, data_dir, permissions)?;
+    if !permissions.contains(&Permission::Network) {
+        deny_network()?;
+    }
+    Ok(enforced)
+}
+
+fn restrict_filesystem(
+    extension_dir: &Path,
+    data_dir: &Path,
+    permissions: &[Permission],
+) -> Result<Enforced, String> {
+    let read = AccessFs::from_read(LANDLOCK_ABI);
+    let all = AccessFs::from_all(LANDLOCK_ABI);
+
+    let granted = permissions.iter().filter_map(|p| match p {
+        Permission::Filesystem(path) => Some(path.as_path()),
+        _ => None,
+    });
+    // Needed to connect to the X server, e.g. for the clipboard
+    let xauthority = env::var_os("XAUTHORITY")
+        .map(Into::into)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".Xauthority")));
+
+    let status = Ruleset::default()
+        .handle_access(all)
+        .and_then(|ruleset| ruleset.create())
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(SYSTEM_DIRS, read)))
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(xauthority, read)))
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules([extension_dir], read)))
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(SYSTEM_FILES, all)))
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules([data_dir], all)))
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(granted, all)))
+        .and_then(|ruleset| ruleset.restrict_self())
+        .map_err(|e| format!("failed to restrict the filesystem: {e}"))?;
+
+    Ok(match status.ruleset {
+        RulesetStatus::FullyEnforced => Enforced::Fully,
+        RulesetStatus::PartiallyEnforced | RulesetStatus::NotEnforced => Enforced::Partially,
+    })
+}
+
+fn deny_network() -> Result<(), String> {
+    let not_unix = SeccompCondition::new(
+        0,
+        SeccompCmpArgLen::Dword,
+        SeccompCmpOp::Ne,
+        libc::AF_UNIX as u64,
+    )
+    .and_then(|condition| SeccompRule::new(vec![condition]))
+    .map_err(|e| e.to_string())?;
+
+    let rules = BTreeMap::from([
+        (libc::SYS_socket, vec![not_unix]),
+        // Could open sockets without the syscall above
+        (libc::SYS_io_uring_setup, Vec::new()),
+    ]);
+    let arch = env::consts::ARCH.try_into().map_err(|e| format!("{e}"))?;
+    let filter: BpfProgram = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EACCES as u32),
+        arch,
+    )
+    .and_then(TryInto::try_into)
+    .map_err(|e| format!("failed to deny networking: {e}"))?;
+
+    seccompiler::apply_filter_all_threads(&filter)
+        .map_err(|e| format!("failed to deny networking: {e}"))
+}
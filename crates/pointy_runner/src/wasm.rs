@@ -0,0 +1,273 @@
+//! Runs WebAssembly component extensions with an embedded runtime.
+//!
+//! Their imports mirror `pointy_api`, with the clipboard and screen gated by the granted
+//! permissions.
+//! Nothing else is reachable, so they can't access files or the network at all.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use pointy_api::{
+    host::{image_from_bytes, image_to_bytes},
+    image::RgbaImage,
+    storage::{self, STORE_FILE},
+    HostCall, Level, Permission,
+};
+use serde_json::Value;
+use wasmtime::{
+    component::{self, Linker},
+    Config, Engine, ResourceLimiter, Store, Trap,
+};
+
+use crate::{calls, Limit, RunnerMessage};
+
+use bindings::pointy::extension::host;
+
+mod bindings {
+    wasmtime::component::bindgen!({ path: "wit", world: "extension" });
+}
+
+/// How often cancelling is checked
+const TICK: Duration = Duration::from_millis(50);
+/// Time the extension gets to return once cancelled, before it is interrupted
+const INTERRUPT_AFTER: Duration = Duration::from_secs(1);
+
+/// A compiled extension
+pub struct Component {
+    engine: Engine,
+    component: component::Component,
+    /// Of its linear memory in bytes
+    memory_limit: Option<u64>,
+}
+
+/// What the imports of a run can reach
+struct State<'a> {
+    data_dir: &'a Path,
+    permissions: &'a [Permission],
+    cancelled: &'a AtomicBool,
+    sink: &'a (dyn Fn(RunnerMessage) + Sync),
+    memory_limit: Option<u64>,
+    /// Set once the memory limit stopped the run
+    exceeded: bool,
+}
+
+impl Component {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).map_err(|e| e.to_string())?;
+        let component = component::Component::from_file(&engine, path).map_err(|e| {
+            format!(
+                "failed to load the WebAssembly component: {}",
+                e.root_cause()
+            )
+        })?;
+        Ok(Self {
+            engine,
+            component,
+            memory_limit: None,
+        })
+    }
+
+    pub fn with_memory_limit(self, bytes: Option<u64>) -> Self {
+        Self {
+            memory_limit: bytes,
+            ..self
+        }
+    }
+
+    /// Instantiates the component and calls its `run` export
+    pub fn call(
+        &self,
+        data_dir: &Path,
+        permissions: &[Permission],
+        cancelled: &AtomicBool,
+        sink: &(dyn Fn(RunnerMessage) + Sync),
+    ) -> Result<(), String> {
+        let state = State {
+            data_dir,
+            permissions,
+            cancelled,
+            sink,
+            memory_limit: self.memory_limit,
+            exceeded: false,
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| state);
+        // Interrupted once the epoch is increased
+        store.set_epoch_deadline(1);
+
+        let mut linker = Linker::new(&self.engine);
+        bindings::Extension::add_to_linker(&mut linker, |state: &mut State| state)
+            .map_err(|e| e.to_string())?;
+
+        let (done, done_rx) = mpsc::channel::<()>();
+        let result = thread::scope(|scope| {
+            scope.spawn(|| interrupt_once_cancelled(&self.engine, cancelled, done_rx));
+            let result = bindings::Extension::instantiate(&mut store, &self.component, &linker)
+                .and_then(|extension| extension.call_run(&mut store));
+            drop(done);
+            result
+        });
+
+        match result {
+            Ok(result) => result,
+            Err(_) if store.data().exceeded => {
+                sink(RunnerMessage::LimitExceeded {
+                    limit: Limit::Memory,
+                });
+                Err("exceeded its memory limit".to_string())
+            }
+            Err(e) => Err(match e.downcast_ref::<Trap>() {
+                Some(Trap::Interrupt) => "interrupted after being cancelled".to_string(),
+                Some(trap) => format!("extension trapped: {trap}"),
+                None => e.to_string(),
+            }),
+        }
+    }
+}
+
+/// Interrupts the run [`INTERRUPT_AFTER`] it was cancelled, unless it is done before
+fn interrupt_once_cancelled(engine: &Engine, cancelled: &AtomicBool, done: mpsc::Receiver<()>) {
+    while let Err(mpsc::RecvTimeoutError::Timeout) = done.recv_timeout(TICK) {
+        if cancelled.load(Ordering::Relaxed) {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = done.recv_timeout(INTERRUPT_AFTER) {
+                engine.increment_epoch();
+            }
+            return;
+        }
+    }
+}
+
+impl State<'_> {
+    /// Performs `call` with the permissions of the run, see [`calls::perform`]
+    fn perform(&self, call: HostCall, input: &[u8]) -> Result<Vec<u8>, String> {
+        calls::perform(self.permissions, call, input)
+    }
+
+    fn perform_image(&self, call: HostCall) -> Result<host::Image, String> {
+        let image = image_from_bytes(self.perform(call, &[])?)?;
+        Ok(host::Image {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+        })
+    }
+}
+
+impl ResourceLimiter for State<'_> {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        if self
+            .memory_limit
+            .is_some_and(|limit| desired as u64 > limit)
+        {
+            self.exceeded = true;
+            return Err(wasmtime::Error::msg("memory limit exceeded"));
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        Ok(true)
+    }
+}
+
+impl host::Host for State<'_> {
+    fn clipboard_get_text(&mut self) -> Result<String, String> {
+        let text = self.perform(HostCall::ClipboardGetText, &[])?;
+        String::from_utf8(text).map_err(|e| e.to_string())
+    }
+
+    fn clipboard_get_image(&mut self) -> Result<host::Image, String> {
+        self.perform_image(HostCall::ClipboardGetImage)
+    }
+
+    fn clipboard_write_text(&mut self, text: String) -> Result<(), String> {
+        self.perform(HostCall::ClipboardWriteText, text.as_bytes())?;
+        Ok(())
+    }
+
+    fn clipboard_write_html(
+        &mut self,
+        html: String,
+        alt_text: Option<String>,
+    ) -> Result<(), String> {
+        let input = serde_json::to_vec(&(html, alt_text)).map_err(|e| e.to_string())?;
+        self.perform(HostCall::ClipboardWriteHtml, &input)?;
+        Ok(())
+    }
+
+    fn clipboard_write_image(&mut self, image: host::Image) -> Result<(), String> {
+        let image = RgbaImage::from_raw(image.width, image.height, image.rgba)
+            .ok_or("the image is smaller than its size")?;
+        self.perform(HostCall::ClipboardWriteImage, &image_to_bytes(&image))?;
+        Ok(())
+    }
+
+    fn clipboard_clear(&mut self) -> Result<(), String> {
+        self.perform(HostCall::ClipboardClear, &[])?;
+        Ok(())
+    }
+
+    fn capture_screen(&mut self) -> Result<host::Image, String> {
+        self.perform_image(HostCall::CaptureScreen)
+    }
+
+    fn log(&mut self, level: host::Level, message: String) {
+        let level = match level {
+            host::Level::Error => Level::Error,
+            host::Level::Warn => Level::Warn,
+            host::Level::Info => Level::Info,
+            host::Level::Debug => Level::Debug,
+        };
+        (self.sink)(RunnerMessage::Log {
+            level: level as u32,
+            message,
+        })
+    }
+
+    fn is_cancelled(&mut self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn report_progress(&mut self, fraction: f32, message: String) {
+        let fraction = if fraction.is_nan() { 0. } else { fraction };
+        (self.sink)(RunnerMessage::Progress { fraction, message })
+    }
+
+    fn notify(&mut self, title: String, body: String) {
+        (self.sink)(RunnerMessage::Notify { title, body })
+    }
+
+    fn storage_get(&mut self, key: String) -> Result<Option<String>, String> {
+        let value: Option<Value> = storage::get_in(&self.data_dir.join(STORE_FILE), &key)?;
+        Ok(value.map(|value| value.to_string()))
+    }
+
+    fn storage_set(&mut self, key: String, value: String) -> Result<(), String> {
+        let value: Value =
+            serde_json::from_str(&value).map_err(|e| format!("value isn't valid JSON: {e}"))?;
+        storage::set_in(&self.data_dir.join(STORE_FILE), &key, &value)
+    }
+
+    fn storage_remove(&mut self, key: String) -> Result<bool, String> {
+        storage::remove_in(&self.data_dir.join(STORE_FILE), &key)
+    }
+}
@@ -0,0 +1,66 @@
+//! Builds the fixtures, each test crate uses only some of these.
+
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use pointy_runner::ExtensionKind;
+
+/// A built fixture, its directory is removed when dropped
+pub struct Fixture {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl Fixture {
+    /// Creates a directory of its own for the fixture `name`, to be written to `filename`
+    fn new(name: &str, filename: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("pointy-{name}-{}-{count}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(filename);
+        Self { dir, path }
+    }
+}
+
+impl Deref for Fixture {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Builds the extension `fixtures/<name>.rs` into a directory of its own
+#[allow(dead_code)]
+pub fn build_fixture(name: &str) -> Fixture {
+    let fixture = Fixture::new(name, &format!("lib{name}.so"));
+    let status = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+        .args(["--crate-type", "cdylib", "--edition", "2021", "-o"])
+        .arg(&fixture.path)
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/fixtures/{name}.rs")))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    fixture
+}
+
+/// Builds the component `fixtures/<name>.wat` into a directory of its own
+#[allow(dead_code)]
+pub fn build_component(name: &str) -> Fixture {
+    let fixture = Fixture::new(name, ExtensionKind::Wasm.filename());
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/fixtures/{name}.wat"));
+    fs::write(&fixture.path, wat::parse_file(source).unwrap()).unwrap();
+    fixture
+}
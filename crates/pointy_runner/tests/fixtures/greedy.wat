@@ -0,0 +1,15 @@
+;; A WebAssembly extension growing its memory for as long as it can.
+(component
+  (core module $m
+    (memory (export "memory") 1)
+    (func (export "run") (result i32)
+      (loop
+        (br_if 0 (i32.ne (memory.grow (i32.const 16)) (i32.const -1))))
+      ;; Points to a zeroed result, so no error
+      (i32.const 0))
+  )
+  (core instance $m (instantiate $m))
+
+  (func (export "run") (result (result (error string)))
+    (canon lift (core func $m "run") (memory $m "memory")))
+)
@@ -0,0 +1,12 @@
+//! An extension allocating more memory than it could ever get.
+
+use std::os::raw::c_char;
+
+#[no_mangle]
+pub extern "C" fn run() -> *mut c_char {
+    let mut chunks = Vec::new();
+    loop {
+        // Touched, so it is actually allocated
+        chunks.push(vec![1u8; 64 << 20]);
+    }
+}
@@ -0,0 +1,38 @@
+//! An extension trying what it wasn't granted, reporting what it got away with as its error.
+//! It tries once from a constructor, run as soon as it is loaded, and once when it is run.
+
+use std::{ffi::CString, fs, io, net::UdpSocket, os::raw::c_char, sync::OnceLock};
+
+/// What the constructor got away with
+static LOADED: OnceLock<String> = OnceLock::new();
+
+#[used]
+#[link_section = ".init_array"]
+static CONSTRUCTOR: extern "C" fn() = constructor;
+
+extern "C" fn constructor() {
+    let _ = LOADED.set(attempt());
+}
+
+#[no_mangle]
+pub extern "C" fn run() -> *mut c_char {
+    let loaded = LOADED.get().map_or("not run", String::as_str);
+    let report = format!("load: {loaded}; run: {}", attempt());
+    CString::new(report).unwrap().into_raw()
+}
+
+fn attempt() -> String {
+    let home = std::env::var("HOME").unwrap_or_default();
+    format!(
+        "home: {}, socket: {}",
+        outcome(fs::read_dir(home)),
+        outcome(UdpSocket::bind("127.0.0.1:0"))
+    )
+}
+
+fn outcome<T>(result: io::Result<T>) -> &'static str {
+    match result {
+        Ok(_) => "allowed",
+        Err(_) => "denied",
+    }
+}
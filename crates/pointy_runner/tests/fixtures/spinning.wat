@@ -0,0 +1,13 @@
+;; A WebAssembly extension which never returns, not even once cancelled.
+(component
+  (core module $m
+    (memory (export "memory") 1)
+    (func (export "run") (result i32)
+      (loop (br 0))
+      unreachable)
+  )
+  (core instance $m (instantiate $m))
+
+  (func (export "run") (result (result (error string)))
+    (canon lift (core func $m "run") (memory $m "memory")))
+)
@@ -0,0 +1,52 @@
+;; A WebAssembly extension storing a greeting and copying it to the clipboard,
+;; returning the result of the latter.
+(component
+  (import "pointy:extension/host@0.1.0" (instance $host
+    (export "storage-set"
+      (func (param "key" string) (param "value" string) (result (result (error string)))))
+    (export "clipboard-write-text"
+      (func (param "text" string) (result (result (error string)))))
+  ))
+
+  ;; Memory and a bump allocator for the strings passed by the host
+  (core module $libc
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $ptr) (local.get 3)))
+      (local.get $ptr))
+  )
+  (core instance $libc (instantiate $libc))
+
+  (core func $storage-set
+    (canon lower (func $host "storage-set")
+      (memory $libc "memory") (realloc (func $libc "realloc"))))
+  (core func $clipboard-write-text
+    (canon lower (func $host "clipboard-write-text")
+      (memory $libc "memory") (realloc (func $libc "realloc"))))
+
+  (core module $main
+    (import "libc" "memory" (memory 1))
+    (import "host" "storage-set" (func $storage-set (param i32 i32 i32 i32 i32)))
+    (import "host" "clipboard-write-text" (func $clipboard-write-text (param i32 i32 i32)))
+    ;; "greeting" at 0, "\"hello\"" at 8 and "hello" at 15
+    (data (i32.const 0) "greeting\"hello\"hello")
+    (func (export "run") (result i32)
+      (call $storage-set (i32.const 0) (i32.const 8) (i32.const 8) (i32.const 7) (i32.const 64))
+      (call $clipboard-write-text (i32.const 15) (i32.const 5) (i32.const 64))
+      (i32.const 64))
+  )
+  (core instance $main (instantiate $main
+    (with "libc" (instance $libc))
+    (with "host" (instance
+      (export "storage-set" (func $storage-set))
+      (export "clipboard-write-text" (func $clipboard-write-text))))))
+
+  (func (export "run") (result (result (error string)))
+    (canon lift (core func $main "run") (memory $libc "memory")))
+)
@@ -0,0 +1,139 @@
+#![cfg(unix)]
+
+use std::{
+    fs,
+    ops::Deref,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
+
+use pointy_runner::{run, ExtensionKind, Limits, RunError, RunOptions, RunOutcome, Stream};
+
+const RUNNER: &str = env!("CARGO_BIN_EXE_pointy-runner");
+
+#[cfg(target_os = "linux")]
+mod common;
+
+fn run_with(program: &Path, lib_path: &Path, timeout: Duration) -> RunOutcome {
+    run_limited(program, lib_path, timeout, Limits::default())
+}
+
+fn run_limited(program: &Path, lib_path: &Path, timeout: Duration, limits: Limits) -> RunOutcome {
+    let cancelled = AtomicBool::new(false);
+    let options = RunOptions {
+        program,
+        kind: ExtensionKind::Native,
+        path: lib_path,
+        data_dir: &std::env::temp_dir(),
+        permissions: &[],
+        timeout,
+        limits,
+        cancelled: &cancelled,
+    };
+    run(&options, |_| {}).unwrap()
+}
+
+/// A shell script standing in for a runner, removed when dropped
+struct Script(PathBuf);
+
+impl Deref for Script {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Script {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Writes a shell script standing in for a runner
+fn script(name: &str, body: &str) -> Script {
+    let path = std::env::temp_dir().join(format!("pointy-runner-{}-{name}", std::process::id()));
+    fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    Script(path)
+}
+
+#[test]
+fn missing_libraries_fail() {
+    let outcome = run_with(
+        Path::new(RUNNER),
+        Path::new("/nonexistent/lib.so"),
+        Duration::from_secs(10),
+    );
+    assert!(matches!(outcome.result, Err(RunError::Failed(_))));
+}
+
+#[test]
+fn output_is_captured() {
+    let program = script("output", "echo hello\necho oops >&2\nexit 3");
+    let outcome = run_with(&program, Path::new("lib.so"), Duration::from_secs(10));
+
+    // Exiting without reporting back is a crash
+    assert!(matches!(outcome.result, Err(RunError::Crashed(_))));
+    let lines: Vec<_> = outcome
+        .output
+        .iter()
+        .map(|line| (line.stream, line.text.as_str()))
+        .collect();
+    assert!(lines.contains(&(Stream::Stdout, "hello")));
+    assert!(lines.contains(&(Stream::Stderr, "oops")));
+}
+
+#[test]
+fn stuck_runs_are_killed() {
+    let program = script("stuck", "sleep 30");
+    let start = Instant::now();
+    let outcome = run_with(&program, Path::new("lib.so"), Duration::from_millis(100));
+
+    assert_eq!(outcome.result.unwrap_err(), RunError::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn cpu_time_is_limited() {
+    let program = script("spinning", "while :; do :; done");
+    let limits = Limits {
+        cpu_time: Some(Duration::from_secs(1)),
+        memory: None,
+    };
+    let outcome = run_limited(
+        &program,
+        Path::new("lib.so"),
+        Duration::from_secs(30),
+        limits,
+    );
+
+    assert_eq!(
+        outcome.result.unwrap_err(),
+        RunError::LimitExceeded(pointy_runner::Limit::CpuTime)
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memory_is_limited() {
+    let lib_path = common::build_fixture("hungry");
+    let limits = Limits {
+        cpu_time: None,
+        memory: Some(256 << 20),
+    };
+    let outcome = run_limited(
+        Path::new(RUNNER),
+        &lib_path,
+        Duration::from_secs(30),
+        limits,
+    );
+
+    assert_eq!(
+        outcome.result.unwrap_err(),
+        RunError::LimitExceeded(pointy_runner::Limit::Memory)
+    );
+}
@@ -0,0 +1,67 @@
+#![cfg(target_os = "linux")]
+
+use std::{env, path::Path, sync::atomic::AtomicBool, time::Duration};
+
+use pointy_api::Permission;
+use pointy_runner::{
+    run, ExtensionKind, Limits, RunError, RunOptions, RunnerMessage, PARTIAL_SANDBOX_WARNING,
+};
+
+const RUNNER: &str = env!("CARGO_BIN_EXE_pointy-runner");
+
+mod common;
+
+/// What the malicious extension got away with, `None` if the kernel can't sandbox it
+fn report(permissions: &[Permission]) -> Option<String> {
+    let malicious = common::build_fixture("malicious");
+    let cancelled = AtomicBool::new(false);
+    let options = RunOptions {
+        program: Path::new(RUNNER),
+        kind: ExtensionKind::Native,
+        path: &malicious,
+        data_dir: &env::temp_dir(),
+        permissions,
+        timeout: Duration::from_secs(10),
+        limits: Limits::default(),
+        cancelled: &cancelled,
+    };
+    let mut unsupported = false;
+    let outcome = run(&options, |message| match message {
+        RunnerMessage::Log { message, .. } if message == PARTIAL_SANDBOX_WARNING => {
+            unsupported = true
+        }
+        message => panic!("unexpected message {message:?}"),
+    })
+    .unwrap();
+
+    if unsupported {
+        eprintln!("skipped, Landlock is unsupported by this kernel");
+        return None;
+    }
+    match outcome.result {
+        Err(RunError::Failed(report)) => Some(report),
+        result => panic!("unexpected result {result:?}"),
+    }
+}
+
+#[test]
+fn ungranted_access_is_denied() {
+    if let Some(report) = report(&[]) {
+        assert_eq!(
+            report,
+            "load: home: denied, socket: denied; run: home: denied, socket: denied"
+        );
+    }
+}
+
+#[test]
+fn granted_access_is_allowed() {
+    let home = env::var("HOME").unwrap();
+    let permissions = [Permission::Network, Permission::Filesystem(home.into())];
+    if let Some(report) = report(&permissions) {
+        assert_eq!(
+            report,
+            "load: home: allowed, socket: allowed; run: home: allowed, socket: allowed"
+        );
+    }
+}
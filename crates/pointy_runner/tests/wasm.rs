@@ -0,0 +1,83 @@
+#![cfg(unix)]
+
+use std::{
+    env, fs,
+    path::Path,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
+
+use pointy_runner::{
+    call_extension, run, ExtensionKind, Limit, Limits, RunError, RunOptions, RunOutcome,
+};
+
+const RUNNER: &str = env!("CARGO_BIN_EXE_pointy-runner");
+
+mod common;
+
+fn run_component(path: &Path, data_dir: &Path, limits: Limits) -> RunOutcome {
+    let cancelled = AtomicBool::new(false);
+    let options = RunOptions {
+        program: Path::new(RUNNER),
+        kind: ExtensionKind::Wasm,
+        path,
+        data_dir,
+        permissions: &[],
+        timeout: Duration::from_secs(30),
+        limits,
+        cancelled: &cancelled,
+    };
+    run(&options, |_| {}).unwrap()
+}
+
+#[test]
+fn ungranted_imports_fail() {
+    let path = common::build_component("storing");
+    let data_dir = path.with_file_name("data");
+    fs::create_dir_all(&data_dir).unwrap();
+    let outcome = run_component(&path, &data_dir, Limits::default());
+
+    assert_eq!(
+        outcome.result.unwrap_err(),
+        RunError::Failed("permission clipboard-write was not granted to this extension".into())
+    );
+    // Storage needs no permission
+    let store = fs::read_to_string(data_dir.join(pointy_api::storage::STORE_FILE)).unwrap();
+    assert_eq!(store, r#"{"greeting":"hello"}"#);
+}
+
+#[test]
+fn memory_is_limited() {
+    let path = common::build_component("greedy");
+    let limits = Limits {
+        cpu_time: None,
+        memory: Some(16 << 20),
+    };
+    let outcome = run_component(&path, &env::temp_dir(), limits);
+
+    assert_eq!(
+        outcome.result.unwrap_err(),
+        RunError::LimitExceeded(Limit::Memory)
+    );
+}
+
+#[test]
+fn cancelled_runs_are_interrupted() {
+    let path = common::build_component("spinning");
+    let cancelled = AtomicBool::new(true);
+    let start = Instant::now();
+    let result = call_extension(
+        ExtensionKind::Wasm,
+        &path,
+        &env::temp_dir(),
+        &[],
+        &cancelled,
+        &|_| {},
+    );
+
+    assert_eq!(
+        result.unwrap_err(),
+        "interrupted after being cancelled".to_string()
+    );
+    assert!(start.elapsed() < Duration::from_secs(10));
+}